@@ -0,0 +1,83 @@
+//! Compare collecting every digit on a line against scanning in from both ends.
+//!
+//! Run with `cargo bench`.  Lines are long runs of letters with a digit near each end, which is
+//! the shape of input where scanning from both ends pays off.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use day1::Composition;
+
+/// Build `nlines` lines of roughly `len` bytes, with spelled-out digits sprinkled throughout.
+fn make_input(nlines: usize, len: usize) -> String {
+    let filler = "abcxyzqrst";
+    let mut s = String::new();
+    for i in 0..nlines {
+        s.push_str("two");
+        let mut n = 0;
+        while n < len {
+            s.push_str(filler);
+            if n % 1000 == 0 {
+                s.push_str("seven");
+            }
+            n += filler.len();
+        }
+        s.push_str(&format!("{}eightx\n", i % 10));
+    }
+    s
+}
+
+/// Run `f` repeatedly for about a second and return the mean time per call.
+fn time<F: FnMut() -> u64>(mut f: F) -> Duration {
+    let start = Instant::now();
+    let mut iters = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        iters += 1;
+    }
+    start.elapsed() / iters
+}
+
+fn main() {
+    for (nlines, len) in [(1000, 100), (10, 100_000), (2, 4_000_000)] {
+        let input = make_input(nlines, len);
+        let collect = || {
+            day1::calibration_sum(black_box(&input), day1::digits_and_words, Composition::FirstLast)
+                .unwrap()
+        };
+        let scan = || day1::first_last_sum(black_box(&input), true);
+        assert_eq!(collect(), scan());
+        let t_collect = time(collect);
+        let t_scan = time(scan);
+        println!(
+            "{:>5} lines x {:>8} bytes: collect {:>12?}  scan {:>12?}  speedup {:.1}x",
+            nlines,
+            len,
+            t_collect,
+            t_scan,
+            t_collect.as_secs_f64() / t_scan.as_secs_f64()
+        );
+    }
+
+    // Any option beyond the defaults goes through the `Tokenizer`, which sees every digit of a
+    // long run of digits as its own match.
+    let options = day1::WordOptions { roman_numerals: true, ..day1::WordOptions::default() };
+    for len in [1_000, 40_000, 1_000_000] {
+        let input = format!("{}\n", "1234567890".repeat(len / 10));
+        let tokenize = || {
+            day1::do_day1_part2_with(black_box(&input), Composition::Sum, &options).unwrap()
+        };
+        println!("    1 line  x {:>8} digits: tokenizer {:>12?}", len, time(tokenize));
+    }
+
+    // Fuzzy matching finds a near-miss of "one" at every "onx", and each has to be checked
+    // against the matches overlapping it.
+    let options = day1::WordOptions { max_edits: 1, ..day1::WordOptions::default() };
+    for len in [1_000, 20_000, 80_000] {
+        let input = format!("{}\n", "onx".repeat(len));
+        let fuzzy = || {
+            day1::do_day1_part2_with(black_box(&input), Composition::Sum, &options).unwrap()
+        };
+        println!("    1 line  x {:>8} words:  fuzzy {:>16?}", len, time(fuzzy));
+    }
+}
@@ -0,0 +1,119 @@
+use std::str::FromStr;
+
+/// How the digits found on a line are combined into a calibration value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Composition {
+    /// `10 * first + last`, as in the puzzle.
+    #[default]
+    FirstLast,
+    /// The first N digits followed by the last N digits.
+    /// A line with fewer than N digits uses all of them for both halves.
+    FirstLastN(usize),
+    /// Every digit on the line, concatenated.
+    AllDigits,
+    /// The sum of every digit on the line.
+    Sum,
+}
+
+impl Composition {
+    /// Combine `digits` (each 0..=9) into a calibration value.
+    /// A line with no digits has a value of 0.
+    /// Returns an error if the value does not fit in a u64.
+    pub fn compose(&self, digits: &[u32]) -> Result<u64, String> {
+        if digits.is_empty() {
+            return Ok(0);
+        }
+        match *self {
+            Composition::FirstLast => concat(&[digits[0], digits[digits.len() - 1]]),
+            Composition::FirstLastN(n) => {
+                let n = n.min(digits.len());
+                let first = concat(&digits[..n])?;
+                let last = concat(&digits[digits.len() - n..])?;
+                10_u64
+                    .checked_pow(n as u32)
+                    .and_then(|shift| first.checked_mul(shift))
+                    .and_then(|x| x.checked_add(last))
+                    .ok_or_else(|| overflow(digits))
+            }
+            Composition::AllDigits => concat(digits),
+            Composition::Sum => Ok(digits.iter().map(|&d| d as u64).sum()),
+        }
+    }
+}
+
+/// Shift each digit in from the right.
+fn concat(digits: &[u32]) -> Result<u64, String> {
+    let mut x: u64 = 0;
+    for &d in digits {
+        x = x
+            .checked_mul(10)
+            .and_then(|x| x.checked_add(d as u64))
+            .ok_or_else(|| overflow(digits))?;
+    }
+    Ok(x)
+}
+
+fn overflow(digits: &[u32]) -> String {
+    format!("calibration value with {} digits overflows u64", digits.len())
+}
+
+impl FromStr for Composition {
+    type Err = String;
+
+    /// Parse `first-last`, `first-last-N`, `all` or `sum`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-last" => Ok(Composition::FirstLast),
+            "all" => Ok(Composition::AllDigits),
+            "sum" => Ok(Composition::Sum),
+            _ => {
+                let n = s
+                    .strip_prefix("first-last-")
+                    .ok_or_else(|| format!("unknown composition: {}", s))?
+                    .parse::<usize>()
+                    .map_err(|e| format!("bad digit count in {}: {}", s, e))?;
+                if n == 0 {
+                    return Err(format!("digit count must be positive: {}", s));
+                }
+                Ok(Composition::FirstLastN(n))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_compose() {
+    let digits = [1, 2, 3, 4, 5];
+    assert_eq!(Composition::FirstLast.compose(&digits), Ok(15));
+    assert_eq!(Composition::FirstLastN(1).compose(&digits), Ok(15));
+    assert_eq!(Composition::FirstLastN(2).compose(&digits), Ok(1245));
+    assert_eq!(Composition::AllDigits.compose(&digits), Ok(12345));
+    assert_eq!(Composition::Sum.compose(&digits), Ok(15));
+}
+
+#[test]
+fn test_compose_short_lines() {
+    assert_eq!(Composition::FirstLast.compose(&[7]), Ok(77));
+    assert_eq!(Composition::FirstLastN(3).compose(&[1, 2]), Ok(1212));
+    assert_eq!(Composition::FirstLast.compose(&[]), Ok(0));
+    assert_eq!(Composition::Sum.compose(&[]), Ok(0));
+}
+
+#[test]
+fn test_compose_overflow() {
+    let digits = [9; 20];
+    assert!(Composition::AllDigits.compose(&digits).is_err());
+    assert!(Composition::FirstLastN(10).compose(&digits).is_err());
+    assert_eq!(Composition::Sum.compose(&digits), Ok(180));
+    assert_eq!(Composition::AllDigits.compose(&digits[..19]), Ok(9_999_999_999_999_999_999));
+}
+
+#[test]
+fn test_parse_composition() {
+    assert_eq!("first-last".parse(), Ok(Composition::FirstLast));
+    assert_eq!("first-last-3".parse(), Ok(Composition::FirstLastN(3)));
+    assert_eq!("all".parse(), Ok(Composition::AllDigits));
+    assert_eq!("sum".parse(), Ok(Composition::Sum));
+    assert!("first-last-0".parse::<Composition>().is_err());
+    assert!("middle".parse::<Composition>().is_err());
+}
@@ -0,0 +1,32 @@
+/// Return the Levenshtein distance between `a` and `b`: the fewest single-character
+/// insertions, deletions and substitutions that turn one into the other.
+pub fn edit_distance(a: &[char], b: &[char]) -> usize {
+    // Classic dynamic programming, keeping only the previous row.
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = prev[j] + if ca == cb { 0 } else { 1 };
+            cur[j + 1] = substitute.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[test]
+fn test_edit_distance() {
+    let d = |a: &str, b: &str| {
+        edit_distance(
+            &a.chars().collect::<Vec<_>>(),
+            &b.chars().collect::<Vec<_>>(),
+        )
+    };
+    assert_eq!(d("nine", "nine"), 0);
+    assert_eq!(d("nlne", "nine"), 1);
+    assert_eq!(d("sevn", "seven"), 1);
+    assert_eq!(d("eiight", "eight"), 1);
+    assert_eq!(d("", "two"), 3);
+    assert_eq!(d("kitten", "sitting"), 3);
+}
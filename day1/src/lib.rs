@@ -1,19 +1,57 @@
+mod compose;
+mod fuzzy;
+mod recognise;
+mod scan;
+mod words;
+
+pub use compose::Composition;
+pub use recognise::{CompoundWords, Digits, Recogniser, RomanNumerals, Tokenizer};
+pub use scan::{first_digit, first_last, first_last_sum, last_digit};
+pub use words::{
+    audit, find_matches, words_with, Match, OverlapPolicy, Recovery, SpelledDigits, WordOptions,
+};
+
+/// Return the digits on a line, in order.
+pub fn digits(line: &str) -> Vec<u32> {
+    line.chars().filter_map(|x| x.to_digit(10)).collect()
+}
+
+/// Return the digits on a line, in order, counting spelled-out digits like "one".
+pub fn digits_and_words(line: &str) -> Vec<u32> {
+    // There may be overlaps, like "oneight", and the authors would like us to parse that as {1,8}.
+    words_with(line, &WordOptions::default())
+}
+
+/// Return the sum of calibration values, tokenizing each line with `tokenize`
+/// and combining its digits according to `composition`.
+pub fn calibration_sum(
+    input: &str,
+    tokenize: impl Fn(&str) -> Vec<u32>,
+    composition: Composition,
+) -> Result<u64, String> {
+    let mut total: u64 = 0;
+    for (i, line) in input.split('\n').enumerate() {
+        let value = composition
+            .compose(&tokenize(line))
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        total = total
+            .checked_add(value)
+            .ok_or_else(|| format!("line {}: total overflows u64", i + 1))?;
+    }
+    Ok(total)
+}
+
 /// Return the sum of calibration values.
-pub fn do_day1_part1(input: &str) -> i32 {
-    let mut total: i32 = 0;
-
-    for found in input.split("\n") {
-        if found.len() == 0 { continue }
-        let nums: Vec<_> = found
-            .chars()
-            .filter(|x| x.is_digit(10))
-            .map(|x| x.to_string().parse::<i32>().unwrap())
-            .collect();
-        let twodigitnum = 10 * nums[0] + nums[nums.len()-1];
-        println!("{}", twodigitnum);
-        total += twodigitnum;
+pub fn do_day1_part1(input: &str) -> u64 {
+    do_day1_part1_with(input, Composition::FirstLast).expect("first and last digits fit in u64")
+}
+
+/// Return the sum of calibration values, combining digits according to `composition`.
+pub fn do_day1_part1_with(input: &str, composition: Composition) -> Result<u64, String> {
+    if composition == Composition::FirstLast {
+        return Ok(first_last_sum(input, false));
     }
-    total
+    calibration_sum(input, digits, composition)
 }
 
 #[test]
@@ -28,47 +66,24 @@ treb7uchet
 }
 
 /// Return the sum of calibration values.
-pub fn do_day1_part2(input: &str) -> i32 {
-    let mut total: i32 = 0;
-
-    for found in input.split("\n") {
-        let mut stringslice = &found[0..found.len()];
-        let mut nums: Vec<_>  = vec![];
-        while stringslice.len() > 0 {
-            if stringslice.starts_with("one") {
-                nums.push(1);
-            } else if stringslice.starts_with("two") {
-                nums.push(2);
-            } else if stringslice.starts_with("three") {
-                nums.push(3);
-            } else if stringslice.starts_with("four") {
-                nums.push(4);
-            } else if stringslice.starts_with("five") {
-                nums.push(5);
-            } else if stringslice.starts_with("six") {
-                nums.push(6);
-            } else if stringslice.starts_with("seven") {
-                nums.push(7);
-            } else if stringslice.starts_with("eight") {
-                nums.push(8);
-            } else if stringslice.starts_with("nine") {
-                nums.push(9);
-            } else {
-                let c = stringslice.chars().next().unwrap();
-                if c.is_digit(10) {
-                    nums.push(c.to_string().parse::<i32>().unwrap());
-                }
-            } 
-            // There may be overlaps, like "oneight", and the authors would like us to parse that as {1,8}.
-            // Therefore only advance one character at a time.
-            stringslice = &stringslice[1..];   
-        }
-        if nums.len() < 1 { println!("Continuing"); continue; }
-        let twodigitnum = 10 * nums[0] + nums[nums.len()-1];
-        println!("{}", twodigitnum);
-        total += twodigitnum;
+pub fn do_day1_part2(input: &str) -> u64 {
+    do_day1_part2_with(input, Composition::FirstLast, &WordOptions::default()).expect("first and last digits fit in u64")
+}
+
+/// Return the sum of calibration values, counting spelled-out digits as directed by `options`
+/// and combining them according to `composition`.
+pub fn do_day1_part2_with(
+    input: &str,
+    composition: Composition,
+    options: &WordOptions,
+) -> Result<u64, String> {
+    // Scanning from both ends only agrees with the tokenizer when every overlapping word counts
+    // and words must match exactly.
+    if composition == Composition::FirstLast && *options == WordOptions::default() {
+        return Ok(first_last_sum(input, true));
     }
-    total
+    let tokenizer = Tokenizer::from_options(options);
+    calibration_sum(input, |line| tokenizer.digits(line), composition)
 }
 
 #[test]
@@ -94,3 +109,59 @@ zoneight234
 
 }
 
+
+#[test]
+fn test_do_day1_with_composition() {
+    let test_input = "\
+two1nine
+eightwothree
+abcone2threexyz";
+    assert_eq!(do_day1_part1_with(test_input, Composition::AllDigits), Ok(1 + 2));
+    assert_eq!(do_day1_part2_with(test_input, Composition::AllDigits, &WordOptions::default()), Ok(219 + 823 + 123));
+    assert_eq!(do_day1_part2_with(test_input, Composition::Sum, &WordOptions::default()), Ok(12 + 13 + 6));
+    assert_eq!(do_day1_part2_with(test_input, Composition::FirstLastN(2), &WordOptions::default()), Ok(2119 + 8223 + 1223));
+}
+
+#[test]
+fn test_do_day1_overflow_names_line() {
+    let err = do_day1_part1_with("1\n99999999999999999999", Composition::AllDigits).unwrap_err();
+    assert!(err.starts_with("line 2:"), "{}", err);
+}
+
+#[test]
+fn test_do_day1_part2_overlap_policy() {
+    let test_input = "oneight\ntwone\neightwo";
+    let sum = |overlap| {
+        do_day1_part2_with(test_input, Composition::FirstLast, &WordOptions { overlap, ..Default::default() }).unwrap()
+    };
+    assert_eq!(sum(OverlapPolicy::Overlapping), 18 + 21 + 82);
+    assert_eq!(sum(OverlapPolicy::GreedyConsume), 11 + 22 + 88);
+    assert_eq!(sum(OverlapPolicy::LongestMatch), 88 + 22 + 88);
+}
+
+#[test]
+fn test_do_day1_part2_recovers_ocr_errors() {
+    let test_input = "One2\nnlne8\nx4SEVENx";
+    let sum = |case_insensitive, max_edits| {
+        let options = WordOptions { case_insensitive, max_edits, ..Default::default() };
+        do_day1_part2_with(test_input, Composition::FirstLast, &options).unwrap()
+    };
+    assert_eq!(sum(false, 0), 22 + 88 + 44);
+    assert_eq!(sum(true, 0), 12 + 88 + 47);
+    assert_eq!(sum(true, 1), 12 + 98 + 47);
+}
+
+#[test]
+fn test_do_day1_part2_roman_and_compound() {
+    let test_input = "axivb\nseventeen\nfortytwo3";
+    let options = WordOptions { roman_numerals: true, compound_words: true, ..Default::default() };
+    assert_eq!(
+        do_day1_part2_with(test_input, Composition::FirstLast, &options),
+        Ok(14 + 17 + 43)
+    );
+    // Without them, "axivb" has no digits at all.
+    assert_eq!(
+        do_day1_part2_with(test_input, Composition::FirstLast, &WordOptions::default()),
+        Ok(77 + 23)
+    );
+}
@@ -2,12 +2,17 @@ use std::env;
 use std::fs;
 use std::process;
 
+use day1::{Composition, WordOptions};
+
+const USAGE: &str = "Usage: day1 `part1'|`part2' [--compose first-last|first-last-N|all|sum] \
+[--overlap overlapping|greedy|longest] [--ignore-case] [--fuzzy MAX_EDITS] [--roman] [--compound] [--audit] FILENAME";
+
 // Run as:
-// day1 [part1|part2] input.txt
+// day1 [part1|part2] [--compose SPEC] [--overlap POLICY] [--ignore-case] [--fuzzy N] [--roman] [--compound] [--audit] input.txt
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: {}  `part1'|`part2' FILENAME", args[0]);
+    if args.len() < 3 {
+        println!("{}", USAGE);
         process::exit(1);
     }
     let partnum = match args[1].as_str() {
@@ -15,13 +20,79 @@ fn main() {
         "part2" => Some(2),
         _ => None,
     }
-    .expect("Usage: <arg0>  `part1'|`part2' FILENAME");
-    let file_path = &args[2];
+    .expect(USAGE);
+    let mut composition = Composition::default();
+    let mut options = WordOptions::default();
+    let mut show_audit = false;
+    let mut file_path = None;
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--compose" => {
+                composition = rest
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|e| {
+                        println!("{}", e);
+                        process::exit(1);
+                    })
+            }
+            "--overlap" if partnum == 2 => {
+                options.overlap = rest
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|e| {
+                        println!("{}", e);
+                        process::exit(1);
+                    })
+            }
+            "--ignore-case" if partnum == 2 => options.case_insensitive = true,
+            "--fuzzy" if partnum == 2 => {
+                options.max_edits = rest
+                    .next()
+                    .expect(USAGE)
+                    .parse()
+                    .unwrap_or_else(|e| {
+                        println!("bad edit distance: {}", e);
+                        process::exit(1);
+                    })
+            }
+            "--roman" if partnum == 2 => options.roman_numerals = true,
+            "--compound" if partnum == 2 => options.compound_words = true,
+            "--audit" if partnum == 2 => show_audit = true,
+            "--overlap" | "--ignore-case" | "--fuzzy" | "--roman" | "--compound" | "--audit" => {
+                println!("{} only applies to part2", arg);
+                process::exit(1);
+            }
+            _ if file_path.is_none() => file_path = Some(arg),
+            _ => {
+                println!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+    }
+    let file_path = file_path.expect(USAGE);
     let input = fs::read_to_string(file_path).expect("Should have been able to read the file");
+    if show_audit {
+        for r in day1::audit(&input, &options) {
+            println!(
+                "line {}: {:?} read as {} (confidence {:.2})",
+                r.line, r.text, r.value, r.confidence
+            );
+        }
+    }
     let output = match partnum {
-        1 => day1::do_day1_part1(&input),
-        2 => day1::do_day1_part2(&input),
+        1 => day1::do_day1_part1_with(&input, composition),
+        2 => day1::do_day1_part2_with(&input, composition, &options),
         _ => unreachable!(),
     };
-    println!("{}", output)
+    match output {
+        Ok(total) => println!("{}", total),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
 }
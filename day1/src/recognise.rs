@@ -0,0 +1,264 @@
+//! Pluggable recognisers for the numbers that make up a calibration value.
+//!
+//! A `Recogniser` looks for one kind of token (digits, spelled-out digits, Roman numerals...)
+//! starting at a given position.  A `Tokenizer` runs a set of recognisers at every position
+//! of a line, settles which matches count, and turns them into digits for a `Composition`.
+//!
+//! A token's value may have more than one digit, like "xiv" (14).  It contributes all of its
+//! decimal digits in order, so its first digit can be the line's first digit and its last digit
+//! the line's last: "axivb" has a calibration value of 14.
+
+use crate::words::{drop_weaker_fuzzy_matches, Match, OverlapPolicy, SpelledDigits, WordOptions};
+
+/// Finds one kind of token in calibration text.
+pub trait Recogniser {
+    /// Return the token that starts at byte offset `start` of `line`, if any.
+    /// `start` is always on a character boundary.
+    fn recognise_at(&self, line: &str, start: usize) -> Option<Match>;
+}
+
+/// Recognises the digits 0 to 9.
+pub struct Digits;
+
+impl Recogniser for Digits {
+    fn recognise_at(&self, line: &str, start: usize) -> Option<Match> {
+        let d = line[start..].chars().next()?.to_digit(10)?;
+        Some(Match { start, end: start + 1, value: d as u64, edits: 0 })
+    }
+}
+
+/// Does `rest` start with `word`?  `word` must be lowercase ASCII.
+pub(crate) fn starts_with_word(rest: &str, word: &str, case_insensitive: bool) -> bool {
+    if case_insensitive {
+        rest.len() >= word.len() && rest.as_bytes()[..word.len()].eq_ignore_ascii_case(word.as_bytes())
+    } else {
+        rest.starts_with(word)
+    }
+}
+
+/// Recognises Roman numerals from 1 to 3999 written the usual way, like "xiv" or "mcmxc".
+///
+/// Ordinary words are full of the letters i, v, x, l, c, d and m, so single letters are
+/// ignored unless `min_len` is 1.  Matching is lowercase only unless `case_insensitive` is set.
+pub struct RomanNumerals {
+    pub case_insensitive: bool,
+    pub min_len: usize,
+}
+
+impl Default for RomanNumerals {
+    fn default() -> Self {
+        RomanNumerals { case_insensitive: false, min_len: 2 }
+    }
+}
+
+/// For each decimal place, from thousands down to ones, how each digit is written.
+const ROMAN_PLACES: [(u64, [&str; 10]); 4] = [
+    (1000, ["", "m", "mm", "mmm", "", "", "", "", "", ""]),
+    (100, ["", "c", "cc", "ccc", "cd", "d", "dc", "dcc", "dccc", "cm"]),
+    (10, ["", "x", "xx", "xxx", "xl", "l", "lx", "lxx", "lxxx", "xc"]),
+    (1, ["", "i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix"]),
+];
+
+impl Recogniser for RomanNumerals {
+    fn recognise_at(&self, line: &str, start: usize) -> Option<Match> {
+        let mut end = start;
+        let mut value = 0;
+        for (scale, numerals) in ROMAN_PLACES {
+            // Take the longest spelling of this place's digit, so "ix" is 9 and not 1.
+            let best = (1..10)
+                .filter(|&d| {
+                    !numerals[d].is_empty()
+                        && starts_with_word(&line[end..], numerals[d], self.case_insensitive)
+                })
+                .max_by_key(|&d| numerals[d].len());
+            if let Some(d) = best {
+                end += numerals[d].len();
+                value += scale * d as u64;
+            }
+        }
+        if value == 0 || end - start < self.min_len {
+            return None;
+        }
+        Some(Match { start, end, value, edits: 0 })
+    }
+}
+
+/// Recognises English number words from 10 to 99, like "twelve", "twenty" and "forty-two".
+/// The tens and units may be joined by a hyphen, a space or nothing at all.
+#[derive(Default)]
+pub struct CompoundWords {
+    pub case_insensitive: bool,
+}
+
+const TEENS: [&str; 10] = [
+    "ten", "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen",
+    "eighteen", "nineteen",
+];
+
+const TENS: [&str; 8] = [
+    "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+impl Recogniser for CompoundWords {
+    fn recognise_at(&self, line: &str, start: usize) -> Option<Match> {
+        let rest = &line[start..];
+        let ci = self.case_insensitive;
+        // No teen is a prefix of another, so at most one can match.
+        if let Some(t) = TEENS.iter().position(|w| starts_with_word(rest, w, ci)) {
+            let end = start + TEENS[t].len();
+            return Some(Match { start, end, value: 10 + t as u64, edits: 0 });
+        }
+        let t = TENS.iter().position(|w| starts_with_word(rest, w, ci))?;
+        let mut end = start + TENS[t].len();
+        let mut value = 20 + 10 * t as u64;
+        for sep in ["-", " ", ""] {
+            let after = &line[end..];
+            if !after.starts_with(sep) {
+                continue;
+            }
+            let after = &after[sep.len()..];
+            if let Some(u) = crate::words::WORDS.iter().position(|w| starts_with_word(after, w, ci)) {
+                end += sep.len() + crate::words::WORDS[u].len();
+                value += u as u64 + 1;
+                break;
+            }
+        }
+        Some(Match { start, end, value, edits: 0 })
+    }
+}
+
+/// Runs a set of recognisers over each line and decides which of their matches count.
+pub struct Tokenizer {
+    recognisers: Vec<Box<dyn Recogniser>>,
+    overlap: OverlapPolicy,
+}
+
+impl Tokenizer {
+    /// A tokenizer with no recognisers; add them with `with`.
+    pub fn new(overlap: OverlapPolicy) -> Tokenizer {
+        Tokenizer { recognisers: vec![], overlap }
+    }
+
+    /// Add a recogniser.
+    pub fn with(mut self, recogniser: impl Recogniser + 'static) -> Tokenizer {
+        self.recognisers.push(Box::new(recogniser));
+        self
+    }
+
+    /// The tokenizer for part 2 with `options`: digits and spelled-out digits, plus Roman
+    /// numerals and compound number words if enabled.
+    pub fn from_options(options: &WordOptions) -> Tokenizer {
+        let mut t = Tokenizer::new(options.overlap).with(Digits).with(SpelledDigits {
+            case_insensitive: options.case_insensitive,
+            max_edits: options.max_edits,
+        });
+        if options.compound_words {
+            t = t.with(CompoundWords { case_insensitive: options.case_insensitive });
+        }
+        if options.roman_numerals {
+            t = t.with(RomanNumerals { case_insensitive: options.case_insensitive, ..Default::default() });
+        }
+        t
+    }
+
+    /// Return every match on the line, sorted by start and then longest first, before the
+    /// overlap policy is applied.  Matches nested inside a longer one, like "seven" inside
+    /// "seventeen" or "iv" inside "xiv", are dropped, as are fuzzy matches beaten by a better
+    /// overlapping match.
+    pub fn candidates(&self, line: &str) -> Vec<Match> {
+        let mut matches = vec![];
+        for (i, _) in line.char_indices() {
+            for r in &self.recognisers {
+                if let Some(m) = r.recognise_at(line, i) {
+                    matches.push(m);
+                }
+            }
+        }
+        if matches.iter().any(|m| m.edits > 0) {
+            matches = drop_weaker_fuzzy_matches(matches);
+        }
+        matches.sort_by_key(|m| (m.start, std::cmp::Reverse(m.end)));
+        // Identical spans from different recognisers: keep the first.
+        matches.dedup_by(|b, a| a.start == b.start && a.end == b.end);
+        // Drop matches nested inside another.  Every match starting no later than `m` comes
+        // before it, and one of them contains it exactly when it ends no earlier.
+        let mut furthest = 0;
+        let mut kept = vec![];
+        for m in matches {
+            if m.end > furthest {
+                kept.push(m);
+            }
+            furthest = furthest.max(m.end);
+        }
+        kept
+    }
+
+    /// Return the matches on the line that count under the overlap policy.
+    pub fn matches(&self, line: &str) -> Vec<Match> {
+        self.overlap.resolve(self.candidates(line))
+    }
+
+    /// Return the digits on the line, in order, taking every digit of multi-digit tokens.
+    pub fn digits(&self, line: &str) -> Vec<u32> {
+        self.matches(line).iter().flat_map(|m| m.digits()).collect()
+    }
+}
+
+#[test]
+fn test_roman_numerals() {
+    let r = RomanNumerals::default();
+    let value = |s: &str| r.recognise_at(s, 0).map(|m| (m.value, m.end));
+    assert_eq!(value("xiv"), Some((14, 3)));
+    assert_eq!(value("xivz"), Some((14, 3)));
+    assert_eq!(value("mcmxc"), Some((1990, 5)));
+    assert_eq!(value("ix"), Some((9, 2)));
+    assert_eq!(value("iiii"), Some((3, 3)));
+    assert_eq!(value("i"), None);
+    assert_eq!(value("abc"), None);
+    assert_eq!(value("XIV"), None);
+    let r = RomanNumerals { case_insensitive: true, min_len: 1 };
+    assert_eq!(r.recognise_at("XIV", 0).map(|m| m.value), Some(14));
+    assert_eq!(r.recognise_at("i", 0).map(|m| m.value), Some(1));
+}
+
+#[test]
+fn test_compound_words() {
+    let r = CompoundWords::default();
+    let value = |s: &str| r.recognise_at(s, 0).map(|m| (m.value, m.end));
+    assert_eq!(value("twenty"), Some((20, 6)));
+    assert_eq!(value("twentyone"), Some((21, 9)));
+    assert_eq!(value("forty-two"), Some((42, 9)));
+    assert_eq!(value("ninety nine"), Some((99, 11)));
+    assert_eq!(value("seventeenx"), Some((17, 9)));
+    assert_eq!(value("ten"), Some((10, 3)));
+    assert_eq!(value("seven"), None);
+}
+
+#[test]
+fn test_tokenizer_multi_digit_tokens() {
+    let t = Tokenizer::new(OverlapPolicy::Overlapping)
+        .with(Digits)
+        .with(SpelledDigits::default())
+        .with(CompoundWords::default())
+        .with(RomanNumerals::default());
+    // "iv" and "v" inside "xiv", and "seven" inside "seventeen", do not count separately.
+    assert_eq!(t.digits("axivb"), vec![1, 4]);
+    assert_eq!(t.digits("seventeen3"), vec![1, 7, 3]);
+    assert_eq!(t.digits("twentyoneight"), vec![2, 1, 8]);
+    assert_eq!(t.digits("7pqrstsixteen"), vec![7, 1, 6]);
+}
+
+#[test]
+fn test_tokenizer_custom_recogniser() {
+    /// Recognises "dozen" as 12.
+    struct Dozen;
+    impl Recogniser for Dozen {
+        fn recognise_at(&self, line: &str, start: usize) -> Option<Match> {
+            line[start..]
+                .starts_with("dozen")
+                .then_some(Match { start, end: start + 5, value: 12, edits: 0 })
+        }
+    }
+    let t = Tokenizer::new(OverlapPolicy::Overlapping).with(Digits).with(Dozen);
+    assert_eq!(t.digits("adozen5"), vec![1, 2, 5]);
+}
@@ -0,0 +1,122 @@
+//! Allocation-free scanning for the first and last digit of a line.
+//!
+//! Collecting every digit just to read both ends of the list is wasteful for very long lines.
+//! Instead we scan forward from the start until the first digit, and backward from the end
+//! until the last digit, so a line is only read as far as needed in each direction.
+
+use crate::words::WORDS;
+
+/// Return the first digit on the line, counting spelled-out digits if `words` is set.
+pub fn first_digit(line: &str, words: bool) -> Option<u32> {
+    for (i, c) in line.char_indices() {
+        if let Some(d) = c.to_digit(10) {
+            return Some(d);
+        }
+        if words {
+            if let Some(d) = word_starting_at(line, i) {
+                return Some(d);
+            }
+        }
+    }
+    None
+}
+
+/// Return the last digit on the line, counting spelled-out digits if `words` is set.
+pub fn last_digit(line: &str, words: bool) -> Option<u32> {
+    for (i, c) in line.char_indices().rev() {
+        if let Some(d) = c.to_digit(10) {
+            return Some(d);
+        }
+        if words {
+            if let Some(d) = word_ending_at(line, i + c.len_utf8()) {
+                return Some(d);
+            }
+        }
+    }
+    None
+}
+
+/// Return the value of the spelled-out digit that begins at byte offset `start`, if any.
+fn word_starting_at(line: &str, start: usize) -> Option<u32> {
+    let rest = &line[start..];
+    WORDS
+        .iter()
+        .position(|w| rest.starts_with(w))
+        .map(|i| i as u32 + 1)
+}
+
+/// Return the value of the spelled-out digit that ends just before byte offset `end`, if any.
+fn word_ending_at(line: &str, end: usize) -> Option<u32> {
+    let head = &line[..end];
+    WORDS
+        .iter()
+        .position(|w| head.ends_with(w))
+        .map(|i| i as u32 + 1)
+}
+
+/// Return `10 * first + last` for the line, or `None` if it has no digits.
+pub fn first_last(line: &str, words: bool) -> Option<u64> {
+    let first = first_digit(line, words)?;
+    let last = last_digit(line, words)?;
+    Some(10 * first as u64 + last as u64)
+}
+
+/// Return the sum of `10 * first + last` over all lines, skipping lines with no digits.
+pub fn first_last_sum(input: &str, words: bool) -> u64 {
+    input
+        .split('\n')
+        .filter_map(|line| first_last(line, words))
+        .sum()
+}
+
+#[test]
+fn test_first_last_digit() {
+    assert_eq!(first_digit("pqr3stu8vwx", false), Some(3));
+    assert_eq!(last_digit("pqr3stu8vwx", false), Some(8));
+    assert_eq!(first_digit("abcone2threexyz", false), Some(2));
+    assert_eq!(first_digit("abcone2threexyz", true), Some(1));
+    assert_eq!(last_digit("abcone2threexyz", true), Some(3));
+    assert_eq!(first_digit("nothing", true), None);
+    assert_eq!(last_digit("", true), None);
+}
+
+#[test]
+fn test_last_digit_matches_words_backward() {
+    // The last word is found by where it ends, even when it overlaps an earlier word.
+    assert_eq!(last_digit("oneight", true), Some(8));
+    assert_eq!(last_digit("xtwone", true), Some(1));
+    assert_eq!(last_digit("sevenine", true), Some(9));
+    assert_eq!(last_digit("eightx", true), Some(8));
+    assert_eq!(first_digit("eightwo", true), Some(8));
+    assert_eq!(last_digit("eightwo", true), Some(2));
+}
+
+#[test]
+fn test_first_last_matches_tokenizer() {
+    let lines = [
+        "two1nine",
+        "eightwothree",
+        "xtwone3four",
+        "4nineeightseven2",
+        "zoneight234",
+        "7pqrstsixteen",
+        "treb7uchet",
+        "no digits here",
+        "",
+    ];
+    for line in lines {
+        for words in [false, true] {
+            let digits = if words {
+                crate::digits_and_words(line)
+            } else {
+                crate::digits(line)
+            };
+            let expected = if digits.is_empty() {
+                None
+            } else {
+                Some(10 * digits[0] as u64 + digits[digits.len() - 1] as u64)
+            };
+            assert_eq!(first_last(line, words), expected, "{:?} words={}", line, words);
+        }
+    }
+}
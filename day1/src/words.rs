@@ -0,0 +1,395 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::fuzzy::edit_distance;
+use crate::recognise::{starts_with_word, Recogniser, Tokenizer};
+
+/// Spelled-out digits; the value of each is its index plus one.
+pub(crate) const WORDS: [&str; 9] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+
+/// A number found on a line, spanning bytes `start..end`.
+/// `edits` is how many characters had to change for a fuzzy match; 0 for an exact one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub value: u64,
+    pub edits: usize,
+}
+
+impl Match {
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// How sure we are of this match, from 1.0 for an exact match down towards 0.0 as
+    /// the number of edits approaches the length of the word.
+    pub fn confidence(&self) -> f64 {
+        if self.edits == 0 {
+            return 1.0;
+        }
+        // Only spelled-out digits are matched fuzzily, so this falls back to the span
+        // length only for custom recognisers.
+        let len = (self.value as usize)
+            .checked_sub(1)
+            .and_then(|i| WORDS.get(i))
+            .map_or(self.len(), |w| w.len());
+        1.0 - self.edits as f64 / len as f64
+    }
+
+    /// The decimal digits of the value, most significant first.
+    pub fn digits(&self) -> Vec<u32> {
+        self.value
+            .to_string()
+            .chars()
+            .map(|c| c.to_digit(10).unwrap())
+            .collect()
+    }
+}
+
+/// What to do when spelled-out digits share letters, as in "oneight".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlapPolicy {
+    /// Every word counts, even if it shares letters with another: "oneight" is {1,8}.
+    #[default]
+    Overlapping,
+    /// Reading left to right, a word consumes its letters: "oneight" is {1}.
+    GreedyConsume,
+    /// Where words overlap, only the longest counts, or the leftmost if they are
+    /// the same length: "oneight" is {8} and "twone" is {2}.
+    LongestMatch,
+}
+
+impl OverlapPolicy {
+    /// Drop matches from `matches` (sorted by start) that this policy does not allow.
+    pub fn resolve(&self, matches: Vec<Match>) -> Vec<Match> {
+        match self {
+            OverlapPolicy::Overlapping => matches,
+            OverlapPolicy::GreedyConsume => {
+                let mut kept: Vec<Match> = vec![];
+                for m in matches {
+                    if kept.last().is_none_or(|k| m.start >= k.end) {
+                        kept.push(m);
+                    }
+                }
+                kept
+            }
+            OverlapPolicy::LongestMatch => {
+                let mut by_length = matches;
+                // Stable sort keeps the leftmost first among equal lengths.
+                by_length.sort_by_key(|m| std::cmp::Reverse(m.len()));
+                // Kept matches by start.  They never overlap, so only the last one starting
+                // before `m` ends can overlap it.
+                let mut kept: BTreeMap<usize, Match> = BTreeMap::new();
+                for m in by_length {
+                    if kept.range(..m.end).next_back().is_none_or(|(_, k)| k.end <= m.start) {
+                        kept.insert(m.start, m);
+                    }
+                }
+                kept.into_values().collect()
+            }
+        }
+    }
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = String;
+
+    /// Parse `overlapping`, `greedy` or `longest`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "overlapping" => Ok(OverlapPolicy::Overlapping),
+            "greedy" => Ok(OverlapPolicy::GreedyConsume),
+            "longest" => Ok(OverlapPolicy::LongestMatch),
+            _ => Err(format!("unknown overlap policy: {}", s)),
+        }
+    }
+}
+
+/// Options for recognising spelled-out digits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordOptions {
+    pub overlap: OverlapPolicy,
+    /// Match "One" and "SEVEN" as well as "one" and "seven".
+    pub case_insensitive: bool,
+    /// Also accept text within this many edits of a spelled-out digit, like "nlne".
+    /// 0 turns fuzzy matching off.
+    pub max_edits: usize,
+    /// Also recognise Roman numerals like "xiv"; see `RomanNumerals`.
+    pub roman_numerals: bool,
+    /// Also recognise number words from 10 to 99 like "twenty"; see `CompoundWords`.
+    pub compound_words: bool,
+}
+
+/// Fuzzy matching never accepts a window shorter than this, since every short string is
+/// within an edit or two of some spelled-out digit.
+const MIN_FUZZY_LEN: usize = 3;
+
+/// Recognises spelled-out digits like "one", optionally ignoring case or allowing
+/// `max_edits` mistakes.
+#[derive(Default)]
+pub struct SpelledDigits {
+    pub case_insensitive: bool,
+    pub max_edits: usize,
+}
+
+impl Recogniser for SpelledDigits {
+    fn recognise_at(&self, line: &str, start: usize) -> Option<Match> {
+        let rest = &line[start..];
+        if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        let m = exact_word_at(rest, self.case_insensitive).or_else(|| {
+            if self.max_edits > 0 {
+                fuzzy_word_at(rest, self.case_insensitive, self.max_edits)
+            } else {
+                None
+            }
+        })?;
+        Some(Match { start, end: start + m.end, ..m })
+    }
+}
+
+/// Return every digit and spelled-out digit on the line, sorted by start, including ones
+/// that overlap.
+pub fn find_matches(line: &str, options: &WordOptions) -> Vec<Match> {
+    Tokenizer::from_options(options).candidates(line)
+}
+
+/// Return the spelled-out digit at the start of `rest`, with `end` relative to `rest`.
+fn exact_word_at(rest: &str, case_insensitive: bool) -> Option<Match> {
+    let w = WORDS.iter().position(|w| starts_with_word(rest, w, case_insensitive))?;
+    Some(Match { start: 0, end: WORDS[w].len(), value: w as u64 + 1, edits: 0 })
+}
+
+/// Return the closest spelled-out digit to some prefix of `rest` that is within `k` edits,
+/// with `end` relative to `rest`.  Prefixes containing digits are never considered, so a
+/// fuzzy word cannot swallow a real digit.
+fn fuzzy_word_at(rest: &str, case_insensitive: bool, k: usize) -> Option<Match> {
+    let window: Vec<(usize, char)> = rest
+        .char_indices()
+        .take_while(|(_, c)| !c.is_ascii_digit())
+        .take(WORDS.iter().map(|w| w.len()).max().unwrap() + k)
+        .map(|(i, c)| {
+            if case_insensitive {
+                (i, c.to_ascii_lowercase())
+            } else {
+                (i, c)
+            }
+        })
+        .collect();
+    let chars: Vec<char> = window.iter().map(|&(_, c)| c).collect();
+    let mut best: Option<Match> = None;
+    for (w, word) in WORDS.iter().enumerate() {
+        let word: Vec<char> = word.chars().collect();
+        let shortest = word.len().saturating_sub(k).max(MIN_FUZZY_LEN);
+        let longest = (word.len() + k).min(chars.len());
+        for len in shortest..=longest {
+            let edits = edit_distance(&chars[..len], &word);
+            // Prefer fewer edits, then a window the same length as the word.
+            let better = match best {
+                None => true,
+                Some(b) => edits < b.edits || (edits == b.edits && len == word.len()),
+            };
+            if edits <= k && better {
+                let (i, c) = window[len - 1];
+                let end = i + c.len_utf8();
+                best = Some(Match { start: 0, end, value: w as u64 + 1, edits });
+            }
+        }
+    }
+    best
+}
+
+/// Fuzzy matching finds many near-copies of each word, like "ine" and "nin" inside "nine".
+/// Keep a fuzzy match only if no overlapping match needed fewer edits, or the same number
+/// of edits and started earlier.
+pub(crate) fn drop_weaker_fuzzy_matches(matches: Vec<Match>) -> Vec<Match> {
+    let mut order: Vec<usize> = (0..matches.len()).collect();
+    order.sort_by_key(|&i| (matches[i].edits, matches[i].start));
+    let mut keep = vec![true; matches.len()];
+    // The cells covered by matches with fewer edits than the current ones, as disjoint spans
+    // by start, so only the last one starting before `m` ends can overlap it.
+    let mut covered: BTreeMap<usize, usize> = BTreeMap::new();
+    for level in order.chunk_by(|&i, &j| matches[i].edits == matches[j].edits) {
+        // With the same edits, only an overlapping match starting earlier beats `m`, and one
+        // does exactly when the furthest end of those starting earlier is after its start.
+        let mut furthest = 0;
+        for same_start in level.chunk_by(|&i, &j| matches[i].start == matches[j].start) {
+            for &i in same_start {
+                let m = &matches[i];
+                let beaten = furthest > m.start
+                    || covered.range(..m.end).next_back().is_some_and(|(_, &end)| end > m.start);
+                keep[i] = m.edits == 0 || !beaten;
+            }
+            furthest = same_start.iter().map(|&i| matches[i].end).fold(furthest, usize::max);
+        }
+        for &i in level {
+            let (mut start, mut end) = (matches[i].start, matches[i].end);
+            // Merge with every span it overlaps or touches.
+            while let Some((&s, &e)) = covered.range(..=end).next_back() {
+                if e < start {
+                    break;
+                }
+                covered.remove(&s);
+                (start, end) = (start.min(s), end.max(e));
+            }
+            covered.insert(start, end);
+        }
+    }
+    matches.into_iter().zip(keep).filter(|&(_, k)| k).map(|(m, _)| m).collect()
+}
+
+/// Return the digits on the line, counting spelled-out digits, after applying `options`.
+pub fn words_with(line: &str, options: &WordOptions) -> Vec<u32> {
+    Tokenizer::from_options(options).digits(line)
+}
+
+/// A token that was only recognised because of case-insensitive or fuzzy matching.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovery {
+    /// 1-based line number.
+    pub line: usize,
+    /// The text that was matched, as it appears in the input.
+    pub text: String,
+    pub value: u64,
+    pub confidence: f64,
+}
+
+/// Return every match counted towards a calibration value under `options` that an exact,
+/// case-sensitive match would have missed, so recovered lines can be reviewed.
+pub fn audit(input: &str, options: &WordOptions) -> Vec<Recovery> {
+    let tokenizer = Tokenizer::from_options(options);
+    let mut recoveries = vec![];
+    for (i, line) in input.split('\n').enumerate() {
+        for m in tokenizer.matches(line) {
+            let text = &line[m.start..m.end];
+            // Every recogniser matches lowercase text unless told to ignore case.
+            if m.edits > 0 || text.chars().any(|c| c.is_ascii_uppercase()) {
+                recoveries.push(Recovery {
+                    line: i + 1,
+                    text: text.to_string(),
+                    value: m.value,
+                    confidence: m.confidence(),
+                });
+            }
+        }
+    }
+    recoveries
+}
+
+#[test]
+fn test_find_matches() {
+    assert_eq!(
+        find_matches("a1two", &WordOptions::default()),
+        vec![
+            Match { start: 1, end: 2, value: 1, edits: 0 },
+            Match { start: 2, end: 5, value: 2, edits: 0 },
+        ]
+    );
+}
+
+#[test]
+fn test_case_insensitive() {
+    let options = WordOptions { case_insensitive: true, ..Default::default() };
+    assert_eq!(words_with("One2SEVENx", &options), vec![1, 2, 7]);
+    assert_eq!(words_with("One2SEVENx", &WordOptions::default()), vec![2]);
+}
+
+#[test]
+fn test_fuzzy() {
+    let options = WordOptions { max_edits: 1, ..Default::default() };
+    assert_eq!(words_with("nlne", &options), vec![9]);
+    assert_eq!(words_with("x3sevn", &options), vec![3, 7]);
+    assert_eq!(words_with("eiight4", &options), vec![8, 4]);
+    // Exact words are not duplicated by fuzzy copies of themselves.
+    assert_eq!(words_with("nine", &options), vec![9]);
+    assert_eq!(words_with("abcone2threexyz", &options), vec![1, 2, 3]);
+    // A fuzzy word may not absorb a real digit.
+    assert_eq!(words_with("n1ne", &options), vec![1]);
+
+    let m = find_matches("nlne", &options);
+    assert_eq!(m, vec![Match { start: 0, end: 4, value: 9, edits: 1 }]);
+    assert_eq!(m[0].confidence(), 0.75);
+}
+
+#[test]
+fn test_fuzzy_case_insensitive() {
+    let options = WordOptions { case_insensitive: true, max_edits: 1, ..Default::default() };
+    assert_eq!(words_with("NlNE", &options), vec![9]);
+    assert_eq!(words_with("NlNE", &WordOptions { max_edits: 2, ..Default::default() }), vec![]);
+}
+
+#[test]
+fn test_drop_weaker_fuzzy_matches() {
+    let m = |start, end, edits| Match { start, end, value: 1, edits };
+    // The rule as written: beaten by any overlapping match with fewer edits, or the same
+    // edits and an earlier start.
+    let naive = |matches: &[Match]| -> Vec<Match> {
+        let beats = |o: &Match, m: &Match| {
+            o.start < m.end
+                && m.start < o.end
+                && (o.edits < m.edits || (o.edits == m.edits && o.start < m.start))
+        };
+        let kept = matches.iter().filter(|m| m.edits == 0 || !matches.iter().any(|o| beats(o, m)));
+        kept.copied().collect()
+    };
+    let cases = [
+        vec![m(0, 3, 1), m(1, 4, 1), m(2, 5, 0), m(6, 9, 2), m(7, 8, 1)],
+        // Two with the same start and edits do not beat each other.
+        vec![m(0, 3, 1), m(0, 4, 1), m(3, 6, 1), m(5, 7, 2)],
+        // Spans that only touch do not overlap, even once merged.
+        vec![m(0, 2, 0), m(2, 4, 0), m(4, 6, 1), m(1, 3, 1), m(9, 12, 2), m(3, 5, 2)],
+    ];
+    for matches in cases {
+        assert_eq!(drop_weaker_fuzzy_matches(matches.clone()), naive(&matches), "{:?}", matches);
+    }
+}
+
+#[test]
+fn test_audit() {
+    let options = WordOptions { case_insensitive: true, max_edits: 1, ..Default::default() };
+    let recoveries = audit("two1nine\nOne2nlne", &options);
+    assert_eq!(
+        recoveries,
+        vec![
+            Recovery { line: 2, text: "One".to_string(), value: 1, confidence: 1.0 },
+            Recovery { line: 2, text: "nlne".to_string(), value: 9, confidence: 0.75 },
+        ]
+    );
+}
+
+#[test]
+fn test_overlap_policies() {
+    let cases = [
+        // line, overlapping, greedy, longest
+        ("oneight", vec![1, 8], vec![1], vec![8]),
+        ("twone", vec![2, 1], vec![2], vec![2]),
+        ("eightwo", vec![8, 2], vec![8], vec![8]),
+        ("xtwone3four", vec![2, 1, 3, 4], vec![2, 3, 4], vec![2, 3, 4]),
+        ("sevenineight", vec![7, 9, 8], vec![7, 8], vec![7, 8]),
+    ];
+    for (line, overlapping, greedy, longest) in cases {
+        let with = |overlap| words_with(line, &WordOptions { overlap, ..Default::default() });
+        assert_eq!(with(OverlapPolicy::Overlapping), overlapping, "{}", line);
+        assert_eq!(with(OverlapPolicy::GreedyConsume), greedy, "{}", line);
+        assert_eq!(with(OverlapPolicy::LongestMatch), longest, "{}", line);
+    }
+}
+
+#[test]
+fn test_parse_overlap_policy() {
+    assert_eq!("overlapping".parse(), Ok(OverlapPolicy::Overlapping));
+    assert_eq!("greedy".parse(), Ok(OverlapPolicy::GreedyConsume));
+    assert_eq!("longest".parse(), Ok(OverlapPolicy::LongestMatch));
+    assert!("lazy".parse::<OverlapPolicy>().is_err());
+}
+
+#[test]
+fn test_match_digits() {
+    let m = |value| Match { start: 0, end: 1, value, edits: 0 };
+    assert_eq!(m(7).digits(), vec![7]);
+    assert_eq!(m(1990).digits(), vec![1, 9, 9, 0]);
+}
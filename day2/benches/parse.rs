@@ -0,0 +1,64 @@
+//! Compare parsing every game into a `Vec` against reading them in place.
+//!
+//! Run with `cargo bench`.  The log comes from `simulate`, so it looks like puzzle input, just
+//! much longer.  Throughput is in megabytes of log per second.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Run `f` repeatedly for about a second and return the mean time per call.
+fn time<F: FnMut() -> i32>(mut f: F) -> Duration {
+    let start = Instant::now();
+    let mut iters = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        iters += 1;
+    }
+    start.elapsed() / iters
+}
+
+/// The part 2 answer the way it was found before `GameView`: own every game first.
+fn owned(input: &str) -> i32 {
+    day2::parse_games(input)
+        .unwrap()
+        .iter()
+        .map(|g| {
+            let m = day2::min_bag_for_game(g);
+            m.r * m.g * m.b
+        })
+        .sum()
+}
+
+fn main() {
+    for games in [1_000, 100_000] {
+        let sim = day2::Simulation {
+            bag: vec![
+                ("red".to_string(), 12),
+                ("green".to_string(), 13),
+                ("blue".to_string(), 14),
+            ],
+            games,
+            draws_per_game: 6,
+            seed: 1,
+        };
+        let input = day2::simulate(&sim).unwrap();
+        let mb = input.len() as f64 / 1e6;
+        let cases: [(&str, &dyn Fn() -> i32); 3] = [
+            ("owned", &|| owned(black_box(&input))),
+            ("str views", &|| day2::do_day2_part2(black_box(&input)).unwrap()),
+            ("reader", &|| day2::do_day2_part2_from(black_box(input.as_bytes())).unwrap()),
+        ];
+        assert!(cases.iter().all(|(_, f)| f() == owned(&input)));
+        for (name, f) in cases {
+            let t = time(f);
+            println!(
+                "{:>7} games, {:>6.1} MB: {:<10} {:>12?} {:>8.1} MB/s",
+                games,
+                mb,
+                name,
+                t,
+                mb / t.as_secs_f64()
+            );
+        }
+    }
+}
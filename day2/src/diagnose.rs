@@ -0,0 +1,75 @@
+//! Explaining why a game could not have been played with a bag.
+
+use crate::{Color, ColorCount, Game};
+
+/// A draw that took more cubes of one color than the bag holds.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Violation {
+    /// Index of the draw in `Game::draws`, from 0.
+    pub draw: usize,
+    pub color: Color,
+    /// How many cubes of `color` the draw took.
+    pub drawn: i32,
+    /// How many cubes of `color` the bag holds.
+    pub in_bag: i32,
+}
+
+impl Violation {
+    /// How many more cubes of the color were drawn than the bag holds.
+    pub fn excess(&self) -> i32 {
+        self.drawn - self.in_bag
+    }
+}
+
+/// Return every way in which the game's draws exceed the bag, in order of draw and then color.
+/// An empty list means the game was possible.
+pub fn violations(game: &Game, bag: &ColorCount) -> Vec<Violation> {
+    let mut res = vec![];
+    for (i, d) in game.draws.iter().enumerate() {
+        for color in Color::ALL {
+            if d.get(color) > bag.get(color) {
+                res.push(Violation {
+                    draw: i,
+                    color,
+                    drawn: d.get(color),
+                    in_bag: bag.get(color),
+                });
+            }
+        }
+    }
+    res
+}
+
+/// Return the first way in which the game's draws exceed the bag, if any.
+pub fn first_violation(game: &Game, bag: &ColorCount) -> Option<Violation> {
+    // Games are short, so finding them all is cheap enough.
+    violations(game, bag).into_iter().next()
+}
+
+#[test]
+fn test_violations() {
+    // Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+    let game = Game {
+        id: 3,
+        draws: vec![
+            ColorCount { r: 20, g: 8, b: 6 },
+            ColorCount { r: 4, g: 13, b: 5 },
+            ColorCount { r: 1, g: 5, b: 0 },
+        ],
+    };
+    let bag = ColorCount { r: 12, g: 12, b: 14 };
+    let v = violations(&game, &bag);
+    assert_eq!(
+        v,
+        vec![
+            Violation { draw: 0, color: Color::Red, drawn: 20, in_bag: 12 },
+            Violation { draw: 1, color: Color::Green, drawn: 13, in_bag: 12 },
+        ]
+    );
+    assert_eq!(v[0].excess(), 8);
+    assert_eq!(first_violation(&game, &bag), Some(v[0].clone()));
+
+    let big = ColorCount { r: 20, g: 13, b: 6 };
+    assert_eq!(violations(&game, &big), vec![]);
+    assert_eq!(first_violation(&game, &big), None);
+}
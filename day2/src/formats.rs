@@ -0,0 +1,221 @@
+//! Reading and writing games as puzzle text, JSON or CSV.
+//!
+//! - Text is the puzzle's own format, one game per line.  Writing it always gives the canonical
+//!   form: colors in the order red, green, blue, with zero counts left out, so that parsing,
+//!   printing and parsing again gives the same games.
+//! - JSON is an array of games: `[{"id": 1, "draws": [{"r": 4, "g": 0, "b": 3}, ...]}, ...]`.
+//! - CSV has a header and one row per draw: `game,draw,red,green,blue`, with draws numbered
+//!   from 1 and each game's rows together and in order.  Draw 1 always starts a new game, so
+//!   two games with the same id stay apart.
+//!
+//! A game with no draws can only be written as JSON: as text it would read back with one empty
+//! draw, and CSV has no row to put it in.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::{parse_games, Color, ColorCount, Game};
+
+/// A way of writing down games.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+impl Format {
+    /// Guess the format from a file's extension: `.json`, `.csv`, or anything else for text.
+    pub fn from_path(path: &str) -> Format {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("json") => Format::Json,
+            Some("csv") => Format::Csv,
+            _ => Format::Text,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Return the game as one line of canonical puzzle text, without a newline.
+pub fn format_game(game: &Game) -> String {
+    let draws: Vec<String> = game
+        .draws
+        .iter()
+        .map(|d| {
+            Color::ALL
+                .iter()
+                .filter(|&&c| d.get(c) != 0)
+                .map(|&c| format!("{} {}", d.get(c), c.name()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    format!("Game {}: {}", game.id, draws.join("; "))
+}
+
+/// Parse games written in `format`.
+pub fn read_games(input: &str, format: Format) -> Result<Vec<Game>, String> {
+    match format {
+        Format::Text => parse_games(input),
+        Format::Json => serde_json::from_str(input).map_err(|e| format!("bad JSON: {}", e)),
+        Format::Csv => from_csv(input),
+    }
+}
+
+/// Write games in `format`.  Returns an error if a game has no draws and `format` is not JSON.
+pub fn write_games(games: &[Game], format: Format) -> Result<String, String> {
+    if format != Format::Json {
+        if let Some(g) = games.iter().find(|g| g.draws.is_empty()) {
+            return Err(format!("game {} has no draws, which only JSON can write", g.id));
+        }
+    }
+    Ok(match format {
+        Format::Text => games.iter().map(|g| format_game(g) + "\n").collect(),
+        Format::Json => {
+            serde_json::to_string_pretty(games).expect("games should always serialize") + "\n"
+        }
+        Format::Csv => to_csv(games),
+    })
+}
+
+const CSV_HEADER: &str = "game,draw,red,green,blue";
+
+fn to_csv(games: &[Game]) -> String {
+    let mut s = String::from(CSV_HEADER);
+    s.push('\n');
+    for g in games {
+        for (i, d) in g.draws.iter().enumerate() {
+            s.push_str(&format!("{},{},{},{},{}\n", g.id, i + 1, d.r, d.g, d.b));
+        }
+    }
+    s
+}
+
+fn from_csv(input: &str) -> Result<Vec<Game>, String> {
+    let mut lines = input.lines().enumerate().filter(|(_, l)| !l.trim().is_empty());
+    match lines.next() {
+        Some((_, header)) if header.trim() == CSV_HEADER => {}
+        _ => return Err(format!("CSV should start with `{}`", CSV_HEADER)),
+    }
+    let mut games: Vec<Game> = vec![];
+    for (n, line) in lines {
+        let fields = line
+            .split(',')
+            .map(|f| f.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("line {}: {}", n + 1, e))?;
+        let [id, draw, r, g, b] = fields[..] else {
+            return Err(format!("line {}: expected 5 fields", n + 1));
+        };
+        match games.last_mut() {
+            Some(game) if game.id == id && draw != 1 => game.draws.push(ColorCount { r, g, b }),
+            _ => games.push(Game { id, draws: vec![ColorCount { r, g, b }] }),
+        }
+        let expected = games.last().unwrap().draws.len() as i32;
+        if draw != expected {
+            return Err(format!("line {}: expected draw {} of game {}", n + 1, expected, id));
+        }
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+";
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(Format::from_path("games.json"), Format::Json);
+    assert_eq!(Format::from_path("dir/games.csv"), Format::Csv);
+    assert_eq!(Format::from_path("input.txt"), Format::Text);
+    assert_eq!(Format::from_path("input"), Format::Text);
+    assert_eq!("csv".parse(), Ok(Format::Csv));
+    assert!("xml".parse::<Format>().is_err());
+}
+
+#[test]
+fn test_text_round_trip() {
+    let games = read_games(EXAMPLE, Format::Text).unwrap();
+    let text = write_games(&games, Format::Text).unwrap();
+    assert_eq!(
+        text,
+        "\
+Game 1: 4 red, 3 blue; 1 red, 2 green, 6 blue; 2 green
+Game 2: 2 green, 1 blue; 1 red, 3 green, 4 blue; 1 green, 1 blue
+"
+    );
+    assert_eq!(read_games(&text, Format::Text).unwrap(), games);
+    // Canonical text is a fixed point.
+    let again = write_games(&read_games(&text, Format::Text).unwrap(), Format::Text);
+    assert_eq!(again.unwrap(), text);
+}
+
+#[test]
+fn test_text_round_trip_odd_games() {
+    // Empty draws, zero and negative counts survive a round trip in lenient parsing.
+    let text = "Game 7: ; 0 red, -2 blue\n";
+    let games = read_games(text, Format::Text).unwrap();
+    let text = write_games(&games, Format::Text).unwrap();
+    assert_eq!(read_games(&text, Format::Text).unwrap(), games);
+}
+
+#[test]
+fn test_json_round_trip() {
+    let games = read_games(EXAMPLE, Format::Text).unwrap();
+    let json = write_games(&games, Format::Json).unwrap();
+    assert!(json.contains("\"draws\""));
+    assert_eq!(read_games(&json, Format::Json).unwrap(), games);
+    assert!(read_games("[{\"id\": 1}]", Format::Json).is_err());
+}
+
+#[test]
+fn test_csv_round_trip() {
+    let games = read_games(EXAMPLE, Format::Text).unwrap();
+    let csv = write_games(&games, Format::Csv).unwrap();
+    assert!(csv.starts_with("game,draw,red,green,blue\n1,1,4,0,3\n1,2,1,2,6\n"));
+    assert_eq!(read_games(&csv, Format::Csv).unwrap(), games);
+}
+
+#[test]
+fn test_csv_errors() {
+    assert!(read_games("1,1,1,1,1\n", Format::Csv).is_err());
+    assert!(read_games("game,draw,red,green,blue\n1,1,1,1\n", Format::Csv).is_err());
+    assert!(read_games("game,draw,red,green,blue\n1,2,1,1,1\n", Format::Csv).is_err());
+    assert!(read_games("game,draw,red,green,blue\n1,1,x,1,1\n", Format::Csv).is_err());
+}
+
+#[test]
+fn test_round_trip_duplicate_ids_and_no_draws() {
+    // Game 3 is there twice, and once more with no draws.
+    let json = r#"[
+        {"id": 3, "draws": [{"r": 1, "g": 0, "b": 0}]},
+        {"id": 3, "draws": [{"r": 0, "g": 2, "b": 0}, {"r": 0, "g": 0, "b": 3}]},
+        {"id": 3, "draws": []}
+    ]"#;
+    let games = read_games(json, Format::Json).unwrap();
+    let e = Err("game 3 has no draws, which only JSON can write".to_string());
+    for format in [Format::Text, Format::Csv] {
+        assert_eq!(write_games(&games, format), e);
+        // Without it, the other two read back as they were.
+        let written = write_games(&games[..2], format).unwrap();
+        let json = write_games(&read_games(&written, format).unwrap(), Format::Json).unwrap();
+        assert_eq!(read_games(&json, Format::Json).unwrap(), &games[..2], "{:?}", format);
+    }
+    let written = write_games(&games, Format::Json).unwrap();
+    assert_eq!(read_games(&written, Format::Json).unwrap(), games);
+}
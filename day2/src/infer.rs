@@ -0,0 +1,198 @@
+//! Estimating what was in the bag from the draws that came out of it.
+//!
+//! Each draw is modelled as taking some cubes from the bag at random, without replacement,
+//! and the cubes are put back before the next draw.  So for a bag of `R` red, `G` green and
+//! `B` blue cubes, the chance of drawing `r`, `g` and `b` of them is the multivariate
+//! hypergeometric probability
+//!
+//! ```text
+//!     C(R, r) C(G, g) C(B, b) / C(R + G + B, r + g + b)
+//! ```
+//!
+//! and the likelihood of a bag is the product of that over all draws.  We search every bag
+//! from the smallest possible one up to a limit on each color.
+
+use crate::{min_bag_for_game, ColorCount, Game};
+
+/// The most likely bag, with a 95% confidence interval for each color.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BagEstimate {
+    /// The bag with the highest likelihood.  Where several are equally likely, the smallest.
+    pub estimate: ColorCount,
+    /// The lowest count of each color inside the confidence interval.
+    pub low: ColorCount,
+    /// The highest count of each color inside the confidence interval.  A value equal to the
+    /// search limit means the draws cannot rule out larger bags.
+    pub high: ColorCount,
+    /// The natural log of the likelihood of `estimate`.
+    pub log_likelihood: f64,
+}
+
+/// Half the 95th percentile of the chi-squared distribution with one degree of freedom.
+/// A count is inside the interval if the best bag with that count is at most this much
+/// less log-likely than the best bag overall.
+const HALF_CHI2_95: f64 = 3.841 / 2.0;
+
+/// Return `ln(k!)` for `k` in `0..=n`.
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut t = vec![0.0; n + 1];
+    for k in 1..=n {
+        t[k] = t[k - 1] + (k as f64).ln();
+    }
+    t
+}
+
+/// Return the most likely bag to have produced all the draws of `games`, assumed to be
+/// played with the same bag, searching up to `max_per_color` cubes of each color.
+/// Returns an error if there are no draws, if a draw has a negative count, or if a draw needs
+/// more than `max_per_color`.
+pub fn infer_bag(games: &[Game], max_per_color: i32) -> Result<BagEstimate, String> {
+    let draws: Vec<&ColorCount> = games.iter().flat_map(|g| &g.draws).collect();
+    if draws.is_empty() {
+        return Err("no draws to infer a bag from".to_string());
+    }
+    // A color missing from a draw counts as 0, but fewer than none cannot be drawn.
+    if let Some(d) = draws.iter().find(|d| d.r < 0 || d.g < 0 || d.b < 0) {
+        return Err(format!("a draw has a negative count: {:?}", d));
+    }
+    let mut least = ColorCount { r: 0, g: 0, b: 0 };
+    for g in games {
+        least = crate::max(&least, &min_bag_for_game(g));
+    }
+    if least.r > max_per_color || least.g > max_per_color || least.b > max_per_color {
+        return Err(format!(
+            "a draw needs more than {} cubes of one color; raise the limit",
+            max_per_color
+        ));
+    }
+
+    let cap = max_per_color as usize;
+    let lnf = ln_factorials(3 * cap);
+    let ln_choose = |n: usize, k: usize| lnf[n] - lnf[k] - lnf[n - k];
+    // The log-likelihood separates into a term for each color, less a term for the total, so
+    // sum each over the draws once, for every count it could take.
+    let color_terms = |least: i32, count: fn(&ColorCount) -> i32| -> Vec<f64> {
+        (0..=cap)
+            .map(|n| {
+                if (n as i32) < least {
+                    f64::NEG_INFINITY
+                } else {
+                    draws.iter().map(|d| ln_choose(n, count(d) as usize)).sum()
+                }
+            })
+            .collect()
+    };
+    let fr = color_terms(least.r, |d| d.r);
+    let fg = color_terms(least.g, |d| d.g);
+    let fb = color_terms(least.b, |d| d.b);
+    let total: Vec<f64> = (0..=3 * cap)
+        .map(|n| {
+            draws
+                .iter()
+                .map(|d| {
+                    let k = (d.r + d.g + d.b) as usize;
+                    if k > n {
+                        0.0
+                    } else {
+                        ln_choose(n, k)
+                    }
+                })
+                .sum()
+        })
+        .collect();
+
+    // Best log-likelihood overall, and for each count of each color (the profile likelihood).
+    let mut best = f64::NEG_INFINITY;
+    let mut estimate = least.clone();
+    let mut profile = vec![vec![f64::NEG_INFINITY; cap + 1]; 3];
+    for r in least.r as usize..=cap {
+        for g in least.g as usize..=cap {
+            for b in least.b as usize..=cap {
+                let ll = fr[r] + fg[g] + fb[b] - total[r + g + b];
+                // Allow for rounding, so that equally likely bags go to the smallest.
+                if ll > best + 1e-9 {
+                    best = ll;
+                    estimate = ColorCount { r: r as i32, g: g as i32, b: b as i32 };
+                }
+                for (p, n) in profile.iter_mut().zip([r, g, b]) {
+                    if ll > p[n] {
+                        p[n] = ll;
+                    }
+                }
+            }
+        }
+    }
+
+    let interval = |p: &[f64]| -> (i32, i32) {
+        let inside: Vec<usize> = (0..=cap).filter(|&n| p[n] >= best - HALF_CHI2_95).collect();
+        (inside[0] as i32, inside[inside.len() - 1] as i32)
+    };
+    let (rlo, rhi) = interval(&profile[0]);
+    let (glo, ghi) = interval(&profile[1]);
+    let (blo, bhi) = interval(&profile[2]);
+    Ok(BagEstimate {
+        estimate,
+        low: ColorCount { r: rlo, g: glo, b: blo },
+        high: ColorCount { r: rhi, g: ghi, b: bhi },
+        log_likelihood: best,
+    })
+}
+
+#[test]
+fn test_ln_factorials() {
+    let t = ln_factorials(5);
+    assert_eq!(t[0], 0.0);
+    assert!((t[5] - 120_f64.ln()).abs() < 1e-12);
+}
+
+#[test]
+fn test_infer_bag_single_color_is_smallest() {
+    // Only red cubes ever come out, so every bag with no green or blue is equally likely.
+    let game = Game {
+        id: 1,
+        draws: vec![ColorCount { r: 3, g: 0, b: 0 }, ColorCount { r: 5, g: 0, b: 0 }],
+    };
+    let e = infer_bag(&[game], 20).unwrap();
+    assert_eq!(e.estimate, ColorCount { r: 5, g: 0, b: 0 });
+    assert_eq!(e.low, ColorCount { r: 5, g: 0, b: 0 });
+    assert_eq!(e.high.r, 20);
+    assert_eq!(e.log_likelihood, 0.0);
+}
+
+#[test]
+fn test_infer_bag_even_split() {
+    // Single-cube draws split evenly between red and blue suggest equal counts, but cannot
+    // tell a bag of 1 and 1 from a bag of 10 and 10.
+    let mut draws = vec![];
+    for _ in 0..10 {
+        draws.push(ColorCount { r: 1, g: 0, b: 0 });
+        draws.push(ColorCount { r: 0, g: 0, b: 1 });
+    }
+    let e = infer_bag(&[Game { id: 1, draws }], 10).unwrap();
+    assert_eq!(e.estimate.r, e.estimate.b);
+    assert_eq!(e.estimate.g, 0);
+    assert!(e.low.r >= 1 && e.high.r <= 10);
+}
+
+#[test]
+fn test_infer_bag_full_draws_pin_down_bag() {
+    // Drawing 4 red and 4 blue several times is far more likely if that is the whole bag.
+    let draws = vec![ColorCount { r: 4, g: 0, b: 4 }; 5];
+    let e = infer_bag(&[Game { id: 1, draws }], 30).unwrap();
+    assert_eq!(e.estimate, ColorCount { r: 4, g: 0, b: 4 });
+    assert_eq!(e.low, ColorCount { r: 4, g: 0, b: 4 });
+    assert!(e.high.r < 30 && e.high.b < 30);
+}
+
+#[test]
+fn test_infer_bag_errors() {
+    assert!(infer_bag(&[], 10).is_err());
+    let game = Game { id: 1, draws: vec![ColorCount { r: 11, g: 0, b: 0 }] };
+    assert!(infer_bag(&[game], 10).is_err());
+    // Lenient parsing lets `-1 blue` through.
+    let games = crate::parse_games("Game 1: 2 red, -1 blue").unwrap();
+    assert_eq!(
+        infer_bag(&games, 10).unwrap_err(),
+        "a draw has a negative count: ColorCount { r: 2, g: 0, b: -1 }"
+    );
+}
@@ -1,48 +1,130 @@
 use std::cmp;
+use std::collections::HashSet;
+use std::io::BufRead;
 
-#[derive(PartialEq, Debug)]
-struct ColorCount {
-    r: i32,
-    g: i32,
-    b: i32,
+use serde::{Deserialize, Serialize};
+
+mod diagnose;
+mod formats;
+mod infer;
+mod optimize;
+mod parallel;
+mod query;
+mod simulate;
+mod stats;
+#[cfg(test)]
+mod testgen;
+mod view;
+
+pub use diagnose::{first_violation, violations, Violation};
+pub use formats::{format_game, read_games, write_games, Format};
+pub use infer::{infer_bag, BagEstimate};
+pub use optimize::{best_bag_for_budget, min_bags, pareto_frontier, BagChoice};
+pub use parallel::{do_day2_parallel, do_day2_parallel_part1, do_day2_parallel_part2, Answers};
+pub use query::{
+    parse_query, query, ArithOp, CmpOp, Condition, Query, QueryResult, Reduce, Select, Value,
+};
+pub use simulate::{simulate, Simulation};
+pub use stats::{stats, ColorStats, Correlation, GameBag, Stats, Summary};
+pub use view::{fold_games, game_views, DrawView, GameView};
+
+/// A number of cubes of each color: either one draw from the bag, or the bag itself.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ColorCount {
+    pub r: i32,
+    pub g: i32,
+    pub b: i32,
+}
+
+/// One of the cube colors.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Color {
+    pub const ALL: [Color; 3] = [Color::Red, Color::Green, Color::Blue];
+
+    /// The color as it is written in game logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Blue => "blue",
+        }
+    }
+}
+
+impl ColorCount {
+    /// Return the number of cubes of `color`.
+    pub fn get(&self, color: Color) -> i32 {
+        match color {
+            Color::Red => self.r,
+            Color::Green => self.g,
+            Color::Blue => self.b,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub id: i32,
+    pub draws: Vec<ColorCount>,
+}
+
+/// How strictly to check game logs.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Validation {
+    /// Reject a log with a color repeated in one draw, a count that is not positive, an
+    /// empty draw, or a game id used twice.
+    Strict,
+    /// Accept those, as `parseline` always has: repeated colors are added together, empty
+    /// draws count as no cubes, and every game is kept.  Each one is recorded as a warning.
+    #[default]
+    Lenient,
 }
 
-#[derive(PartialEq, Debug)]
-struct Game {
-    id: i32,
-    draws: Vec<ColorCount>,
+/// Games parsed from a log, and any warnings about them.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ParseReport {
+    pub games: Vec<Game>,
+    /// In lenient mode, problems that strict mode would have rejected.
+    pub warnings: Vec<String>,
 }
 
-/// Return the sum of calibration values.
-fn parseline(input: &str) -> Result<Game, String> {
-    let mut _total: i32 = 0;
+/// Parse one line of the form `Game 1: 3 blue, 4 red; 1 red, 2 green`.
+pub fn parseline(input: &str) -> Result<Game, String> {
+    parse_game(input, &mut vec![])
+}
+
+/// Parse one line, adding anything strict mode would reject to `issues`.
+fn parse_game(input: &str, issues: &mut Vec<String>) -> Result<Game, String> {
+    let view = GameView::parse(input)?;
+    let gameid = view.id;
     let mut draws = vec![];
-    let (start, rest) = input.split_once(":").expect("split line on colon");
-    let gameid = start
-        .strip_prefix("Game ")
-        .expect("game prefix")
-        .parse::<i32>()
-        .expect("parse game id");
-    for drawstr in rest.split(";") {
+    for (i, draw) in view.draws().enumerate() {
         let mut d = ColorCount { r: 0, g: 0, b: 0 };
-        for s in drawstr.split(",") {
-            let (countstr, colorstr) = s.trim().split_once(" ").expect("count and color");
-            let count = countstr
-                .parse::<i32>()
-                .expect("count of color should be i32");
-            *(match colorstr {
-                "red" => &mut d.r,
-                "green" => &mut d.g,
-                "blue" => &mut d.b,
-                _ => panic!("unknown color"),
-            }) += count;
+        if draw.is_empty() {
+            issues.push(format!("game {} draw {} is empty", gameid, i + 1));
+        }
+        let mut seen = vec![];
+        for cube in draw.cubes() {
+            let (count, colorstr) = cube?;
+            if count <= 0 {
+                issues.push(format!("game {} draw {} has {} {}", gameid, i + 1, count, colorstr));
+            }
+            if seen.contains(&colorstr) {
+                issues.push(format!("game {} draw {} repeats {}", gameid, i + 1, colorstr));
+            }
+            seen.push(colorstr);
+            view::add(&mut d, count, colorstr)?;
         }
         draws.push(d)
     }
-    Ok(Game {
-        id: gameid,
-        draws: draws,
-    })
+    Ok(Game { id: gameid, draws })
 }
 
 #[test]
@@ -61,13 +143,105 @@ fn test_parseline() {
     assert_eq!(res.unwrap(), expected);
 }
 
-fn game_possible_with_bag(game: &Game, bag: &ColorCount) -> bool {
-    for d in &game.draws {
-        if d.r > bag.r || d.g > bag.g || d.b > bag.b {
-            return false;
+/// Parse every non-empty line of `input` as a game.
+pub fn parse_games(input: &str) -> Result<Vec<Game>, String> {
+    parse_games_with(input, Validation::Lenient).map(|report| report.games)
+}
+
+/// Parse every non-empty line of `input` as a game, checking it as `validation` says.
+/// Errors and warnings name the line they come from.
+pub fn parse_games_with(input: &str, validation: Validation) -> Result<ParseReport, String> {
+    let mut games: Vec<Game> = vec![];
+    let mut ids = HashSet::new();
+    let mut warnings = vec![];
+    for (n, line) in input.split('\n').enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let mut issues = vec![];
+        let game = parse_game(line, &mut issues).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        if !ids.insert(game.id) {
+            issues.push(format!("game id {} is used more than once", game.id));
+        }
+        for issue in issues {
+            let issue = format!("line {}: {}", n + 1, issue);
+            match validation {
+                Validation::Strict => return Err(issue),
+                Validation::Lenient => warnings.push(issue),
+            }
         }
+        games.push(game);
     }
-    true
+    Ok(ParseReport { games, warnings })
+}
+
+#[test]
+fn test_parseline_errors() {
+    assert!(parseline("Game 1 3 blue").is_err());
+    assert!(parseline("Round 1: 3 blue").is_err());
+    assert!(parseline("Game x: 3 blue").is_err());
+    assert!(parseline("Game 1: blue").is_err());
+    assert!(parseline("Game 1: three blue").is_err());
+    assert!(parseline("Game 1: 3 purple").is_err());
+}
+
+#[test]
+fn test_parse_games_lenient() {
+    let input = "\
+Game 1: 3 red, 4 red; 1 blue
+Game 2: 0 green; ; 2 blue
+Game 2: -1 blue
+";
+    let report = parse_games_with(input, Validation::Lenient).unwrap();
+    assert_eq!(
+        report.games,
+        vec![
+            Game {
+                id: 1,
+                draws: vec![ColorCount { r: 7, g: 0, b: 0 }, ColorCount { r: 0, g: 0, b: 1 }],
+            },
+            Game {
+                id: 2,
+                draws: vec![
+                    ColorCount { r: 0, g: 0, b: 0 },
+                    ColorCount { r: 0, g: 0, b: 0 },
+                    ColorCount { r: 0, g: 0, b: 2 },
+                ],
+            },
+            Game { id: 2, draws: vec![ColorCount { r: 0, g: 0, b: -1 }] },
+        ]
+    );
+    assert_eq!(
+        report.warnings,
+        vec![
+            "line 1: game 1 draw 1 repeats red",
+            "line 2: game 2 draw 1 has 0 green",
+            "line 2: game 2 draw 2 is empty",
+            "line 3: game 2 draw 1 has -1 blue",
+            "line 3: game id 2 is used more than once",
+        ]
+    );
+    assert_eq!(parse_games(input).unwrap(), report.games);
+}
+
+#[test]
+fn test_parse_games_strict() {
+    let ok = "Game 1: 3 blue, 4 red; 1 red\nGame 2: 2 green\n";
+    let report = parse_games_with(ok, Validation::Strict).unwrap();
+    assert_eq!(report.games.len(), 2);
+    assert!(report.warnings.is_empty());
+
+    let err = |input| parse_games_with(input, Validation::Strict).unwrap_err();
+    assert_eq!(err("Game 1: 3 red, 4 red"), "line 1: game 1 draw 1 repeats red");
+    assert_eq!(err("Game 1: 0 red"), "line 1: game 1 draw 1 has 0 red");
+    assert_eq!(err("Game 1: 2 red;"), "line 1: game 1 draw 2 is empty");
+    assert_eq!(err("Game 1: 1 red\nGame 1: 2 red"), "line 2: game id 1 is used more than once");
+    assert_eq!(err("Game 1: 1 puce"), "line 1: unknown color: puce");
+}
+
+/// Return whether every draw of the game fits in the bag.  `first_violation` says why not.
+pub fn game_possible_with_bag(game: &Game, bag: &ColorCount) -> bool {
+    first_violation(game, bag).is_none()
 }
 
 #[test]
@@ -133,7 +307,8 @@ fn test_max() {
     );
 }
 
-fn min_bag_for_game(game: &Game) -> ColorCount {
+/// Return the smallest bag the game could have been played with.
+pub fn min_bag_for_game(game: &Game) -> ColorCount {
     let mut res = ColorCount { r: 0, g: 0, b: 0 };
     for d in &game.draws {
         res = max(d, &res);
@@ -176,25 +351,31 @@ fn test_min_bag_for_game() {
     }
 }
 
-/// Return the sum of calibration values.
-pub fn do_day2_part1(input: &str) -> i32 {
-    let mut total: i32 = 0;
+/// The bag that part 1 asks about.
+pub const PART1_BAG: ColorCount = ColorCount {
+    r: 12,
+    g: 13,
+    b: 14,
+};
 
-    let bag = ColorCount {
-        r: 12,
-        g: 13,
-        b: 14,
-    };
-    for found in input.split("\n") {
-        if found.len() == 0 {
-            continue;
-        }
-        let game = parseline(found).unwrap();
-        if game_possible_with_bag(&game, &bag) {
-            total += game.id
-        }
-    }
-    total
+/// Return the sum of the ids of the games that are possible with the part 1 bag.
+/// Returns an error naming the first line that cannot be read.
+pub fn do_day2_part1(input: &str) -> Result<i32, String> {
+    do_day2_part1_from(input.as_bytes())
+}
+
+fn part1_value(view: &GameView) -> Result<i32, String> {
+    Ok(if view.possible_with_bag(&PART1_BAG)? { view.id } else { 0 })
+}
+
+/// Add a game's value to an answer, or say that it overflowed.
+fn add_value(total: i32, value: i32) -> Result<i32, String> {
+    total.checked_add(value).ok_or_else(|| "overflow".to_string())
+}
+
+/// Return the part 1 answer for a log read a line at a time, in constant memory.
+pub fn do_day2_part1_from<R: BufRead>(reader: R) -> Result<i32, String> {
+    fold_games(reader, 0, |total, view| add_value(total, part1_value(&view)?))
 }
 
 #[test]
@@ -206,22 +387,27 @@ Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
 Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
 ";
-    assert_eq!(do_day2_part1(test_input), 1 + 2 + 5);
+    assert_eq!(do_day2_part1(test_input), Ok(1 + 2 + 5));
+    assert_eq!(do_day2_part1_from(test_input.as_bytes()), Ok(1 + 2 + 5));
+    assert!(do_day2_part1("Game 1: 3 blue\nGame 2: x red\n").unwrap_err().starts_with("line 2: "));
 }
 
-/// Return the sum of calibration values.
-pub fn do_day2_part2(input: &str) -> i32 {
-    let mut total: i32 = 0;
+/// Return the sum of the powers of each game's minimum bag.
+/// Returns an error naming the first line that cannot be read.
+pub fn do_day2_part2(input: &str) -> Result<i32, String> {
+    do_day2_part2_from(input.as_bytes())
+}
 
-    for found in input.split("\n") {
-        if found.len() == 0 {
-            continue;
-        }
-        let game = parseline(found).unwrap();
-        let m = min_bag_for_game(&game);
-        total += m.r * m.g * m.b;
-    }
-    total
+fn part2_value(view: &GameView) -> Result<i32, String> {
+    let m = view.min_bag()?;
+    m.r.checked_mul(m.g)
+        .and_then(|rg| rg.checked_mul(m.b))
+        .ok_or_else(|| "overflow".to_string())
+}
+
+/// Return the part 2 answer for a log read a line at a time, in constant memory.
+pub fn do_day2_part2_from<R: BufRead>(reader: R) -> Result<i32, String> {
+    fold_games(reader, 0, |total, view| add_value(total, part2_value(&view)?))
 }
 
 #[test]
@@ -233,5 +419,6 @@ Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
 Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
 Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
 ";
-    assert_eq!(do_day2_part2(test_input), 2286);
+    assert_eq!(do_day2_part2(test_input), Ok(2286));
+    assert_eq!(do_day2_part2_from(test_input.as_bytes()), Ok(2286));
 }
@@ -1,27 +1,355 @@
 use std::env;
-use std::fs;
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::process;
 
+use day2::Format;
+
+const USAGE: &str = "\
+Usage: day2 [--format text|json|csv] COMMAND ...
+
+Commands:
+       day2 `part1'|`part2' [--strict] [--jobs N] FILENAME
+       day2 check [--strict] FILENAME
+       day2 why [--bag RED,GREEN,BLUE] GAME_ID FILENAME
+       day2 stats [--json] FILENAME
+       day2 simulate --bag COLOR=N,... [--games N] [--draws N] [--seed N]
+       day2 infer [--max CUBES_PER_COLOR] FILENAME
+       day2 optimize [--cubes TOTAL] FILENAME
+       day2 convert --to text|json|csv FILENAME
+       day2 query QUERY FILENAME
+
+Without --format, files ending in .json or .csv are read as JSON or CSV.
+optimize chooses among red, green and blue, the only colors a game log records.";
+
+fn usage() -> ! {
+    println!("{}", USAGE);
+    process::exit(1);
+}
+
+/// Read a game log as puzzle text, converting it first if it is written in another format.
+fn read_input(file_path: &str, format: Option<Format>) -> String {
+    let input = fs::read_to_string(file_path).expect("Should have been able to read the file");
+    match format.unwrap_or_else(|| Format::from_path(file_path)) {
+        Format::Text => input,
+        format => day2::write_games(&parse_or_exit(&input, format), Format::Text)
+            .unwrap_or_else(|e| {
+                println!("{}", e);
+                process::exit(1);
+            }),
+    }
+}
+
+fn read_games(file_path: &str, format: Option<Format>) -> Vec<day2::Game> {
+    let input = fs::read_to_string(file_path).expect("Should have been able to read the file");
+    parse_or_exit(&input, format.unwrap_or_else(|| Format::from_path(file_path)))
+}
+
+fn parse_or_exit(input: &str, format: Format) -> Vec<day2::Game> {
+    day2::read_games(input, format).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    })
+}
+
 // Run as:
-// day2 [part1|part2] input.txt
+// day2 [--format json] [part1|part2] [--strict] [--jobs 4] input.txt
+// day2 check [--strict] input.txt
+// day2 why [--bag R,G,B] ID input.txt
+// day2 stats [--json] input.txt
+// day2 simulate --bag red=12,green=13,blue=14 [--games 100] [--draws 5] [--seed 1] > log.txt
+// day2 infer [--max N] input.txt
+// day2 optimize [--cubes N] input.txt
+// day2 convert --to json input.txt > games.json
+// day2 query 'games where max(red) > 10 and draws >= 4' input.txt
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: {}  `part1'|`part2' FILENAME", args[0]);
+    let mut args: Vec<String> = env::args().collect();
+    let mut format = None;
+    if args.len() >= 3 && args[1] == "--format" {
+        format = Some(args[2].parse().unwrap_or_else(|_| usage()));
+        args.drain(1..3);
+    }
+    if args.len() < 2 {
+        usage();
+    }
+    match args[1].as_str() {
+        "part1" | "part2" => parts(&args[1], &args[2..], format),
+        "check" => check(&args[2..], format),
+        "why" => why(&args[2..], format),
+        "stats" => stats(&args[2..], format),
+        "simulate" => simulate(&args[2..]),
+        "infer" => infer(&args[2..], format),
+        "optimize" => optimize(&args[2..], format),
+        "convert" => convert(&args[2..], format),
+        "query" => query(&args[2..], format),
+        _ => usage(),
+    }
+}
+
+/// Split off a leading `--strict` flag.
+fn validation(args: &[String]) -> (day2::Validation, &[String]) {
+    match args {
+        [flag, rest @ ..] if flag == "--strict" => (day2::Validation::Strict, rest),
+        _ => (day2::Validation::Lenient, args),
+    }
+}
+
+fn parts(part: &str, args: &[String], format: Option<Format>) {
+    let (validation, args) = validation(args);
+    let (jobs, file_path) = match args {
+        [flag, n, file_path] if flag == "--jobs" => {
+            (Some(n.parse::<usize>().unwrap_or_else(|_| usage())), file_path)
+        }
+        [file_path] => (None, file_path),
+        _ => usage(),
+    };
+    let streaming = validation == day2::Validation::Lenient
+        && format.unwrap_or_else(|| Format::from_path(file_path)) == Format::Text;
+    let output = if let Some(jobs) = jobs {
+        let input = read_input(file_path, format);
+        // Lenient parsing cannot fail where the parallel parts succeed, so only check when strict.
+        let checked = match validation {
+            day2::Validation::Strict => day2::parse_games_with(&input, validation).map(|_| ()),
+            day2::Validation::Lenient => Ok(()),
+        };
+        checked.and_then(|_| match part {
+            "part1" => day2::do_day2_parallel_part1(&input, jobs),
+            "part2" => day2::do_day2_parallel_part2(&input, jobs),
+            _ => unreachable!(),
+        })
+    } else if streaming {
+        // Read a line at a time, so that logs of any size fit in memory.
+        let file = File::open(file_path).expect("Should have been able to read the file");
+        let reader = BufReader::new(file);
+        match part {
+            "part1" => day2::do_day2_part1_from(reader),
+            "part2" => day2::do_day2_part2_from(reader),
+            _ => unreachable!(),
+        }
+    } else {
+        let input = read_input(file_path, format);
+        day2::parse_games_with(&input, validation).and_then(|_| match part {
+            "part1" => day2::do_day2_part1(&input),
+            "part2" => day2::do_day2_part2(&input),
+            _ => unreachable!(),
+        })
+    };
+    match output {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print any problems with a game log: the first error in strict mode, or every warning.
+fn check(args: &[String], format: Option<Format>) {
+    let (validation, args) = validation(args);
+    if args.len() != 1 {
+        usage();
+    }
+    match day2::parse_games_with(&read_input(&args[0], format), validation) {
+        Ok(report) => {
+            for w in &report.warnings {
+                println!("{}", w);
+            }
+            println!("{} games, {} warnings", report.games.len(), report.warnings.len());
+        }
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Explain why a game was impossible with the part 1 bag, or another bag.
+fn why(args: &[String], format: Option<Format>) {
+    let (bag, id, file_path) = match args {
+        [flag, bag, id, file_path] if flag == "--bag" => {
+            let counts: Vec<i32> = bag
+                .split(',')
+                .map(|n| n.parse::<i32>().unwrap_or_else(|_| usage()))
+                .collect();
+            if counts.len() != 3 {
+                usage();
+            }
+            let bag = day2::ColorCount { r: counts[0], g: counts[1], b: counts[2] };
+            (bag, id, file_path)
+        }
+        [id, file_path] => (day2::PART1_BAG, id, file_path),
+        _ => usage(),
+    };
+    let id = id.parse::<i32>().unwrap_or_else(|_| usage());
+    let games = read_games(file_path, format);
+    let Some(game) = games.iter().find(|g| g.id == id) else {
+        println!("No game {} in {}", id, file_path);
         process::exit(1);
+    };
+    let bag_str = format!("{} red, {} green, {} blue", bag.r, bag.g, bag.b);
+    let violations = day2::violations(game, &bag);
+    if violations.is_empty() {
+        println!("Game {} is possible with {}", id, bag_str);
+        return;
     }
-    let partnum = match args[1].as_str() {
-        "part1" => Some(1),
-        "part2" => Some(2),
-        _ => None,
+    println!("Game {} is impossible with {}:", id, bag_str);
+    for v in violations {
+        println!(
+            "  draw {}: {} {}, {} more than the bag holds",
+            v.draw + 1,
+            v.drawn,
+            v.color.name(),
+            v.excess()
+        );
     }
-    .expect("Usage: <arg0>  `part1'|`part2' FILENAME");
-    let file_path = &args[2];
-    let input = fs::read_to_string(file_path).expect("Should have been able to read the file");
-    let output = match partnum {
-        1 => day2::do_day2_part1(&input),
-        2 => day2::do_day2_part2(&input),
-        _ => unreachable!(),
+}
+
+/// Print statistics about a game log as a table, or as JSON.
+fn stats(args: &[String], format: Option<Format>) {
+    let (json, file_path) = match args {
+        [flag, file_path] if flag == "--json" => (true, file_path),
+        [file_path] => (false, file_path),
+        _ => usage(),
+    };
+    let stats = day2::stats(&read_games(file_path, format)).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    if json {
+        println!("{}", stats.to_json());
+    } else {
+        print!("{}", stats);
+    }
+}
+
+/// Print a game log played with a known bag.
+fn simulate(args: &[String]) {
+    let mut sim = day2::Simulation {
+        bag: vec![],
+        games: 100,
+        draws_per_game: 5,
+        seed: 1,
+    };
+    for pair in args.chunks(2) {
+        let [flag, value] = pair else { usage() };
+        let number = || value.parse().unwrap_or_else(|_| usage());
+        match flag.as_str() {
+            "--bag" => {
+                for part in value.split(',') {
+                    let (color, n) = part.split_once('=').unwrap_or_else(|| usage());
+                    let n = n.parse::<i32>().unwrap_or_else(|_| usage());
+                    sim.bag.push((color.to_string(), n));
+                }
+            }
+            "--games" => sim.games = number() as usize,
+            "--draws" => sim.draws_per_game = number() as usize,
+            "--seed" => sim.seed = number(),
+            _ => usage(),
+        }
+    }
+    match day2::simulate(&sim) {
+        Ok(log) => print!("{}", log),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the most likely bag for each game, and for all games played with one bag.
+fn infer(args: &[String], format: Option<Format>) {
+    let (max_per_color, file_path) = match args {
+        [flag, n, file_path] if flag == "--max" => {
+            (n.parse::<i32>().unwrap_or_else(|_| usage()), file_path)
+        }
+        [file_path] => (100, file_path),
+        _ => usage(),
     };
-    println!("{}", output)
+    let games = read_games(file_path, format);
+    let print = |label: &str, games: &[day2::Game]| match day2::infer_bag(games, max_per_color) {
+        Ok(e) => println!(
+            "{}: red {} [{}, {}]  green {} [{}, {}]  blue {} [{}, {}]",
+            label,
+            e.estimate.r,
+            e.low.r,
+            e.high.r,
+            e.estimate.g,
+            e.low.g,
+            e.high.g,
+            e.estimate.b,
+            e.low.b,
+            e.high.b
+        ),
+        Err(e) => println!("{}: {}", label, e),
+    };
+    for g in &games {
+        print(&format!("Game {}", g.id), std::slice::from_ref(g));
+    }
+    print("All games", &games);
+}
+
+/// Print the bag of a given size that makes the most games possible, or without a size,
+/// the most games possible for every bag size where that changes.
+fn optimize(args: &[String], format: Option<Format>) {
+    let (cubes, file_path) = match args {
+        [flag, n, file_path] if flag == "--cubes" => {
+            (Some(n.parse::<i64>().unwrap_or_else(|_| usage())), file_path)
+        }
+        [file_path] => (None, file_path),
+        _ => usage(),
+    };
+    let min_bags = day2::min_bags(&read_games(file_path, format));
+    // With no games there are no colors either, and the bag is empty.
+    let print = |c: &day2::BagChoice| {
+        let counts: Vec<String> = ["red", "green", "blue"]
+            .iter()
+            .zip(&c.bag)
+            .map(|(color, n)| format!("{} {} ", color, n))
+            .collect();
+        println!("{} cubes: {}admits {} games", c.cubes, counts.concat(), c.games)
+    };
+    let result = match cubes {
+        Some(cubes) => day2::best_bag_for_budget(&min_bags, cubes).map(|c| vec![c]),
+        None => day2::pareto_frontier(&min_bags),
+    };
+    match result {
+        Ok(choices) => choices.iter().for_each(print),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print a game log in another format.
+fn convert(args: &[String], format: Option<Format>) {
+    let (to, file_path) = match args {
+        [flag, to, file_path] if flag == "--to" => {
+            (to.parse::<Format>().unwrap_or_else(|_| usage()), file_path)
+        }
+        _ => usage(),
+    };
+    match day2::write_games(&read_games(file_path, format), to) {
+        Ok(written) => print!("{}", written),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Print the ids of the games a query matches, or the number it works out.
+fn query(args: &[String], format: Option<Format>) {
+    let [query, file_path] = args else { usage() };
+    let query = day2::parse_query(query).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    match query.run(&read_games(file_path, format)) {
+        Ok(result) => print!("{}", result),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,201 @@
+//! Choosing a bag of a given size that makes as many games possible as it can.
+//!
+//! A game is possible with a bag exactly when the bag holds at least the game's minimum bag
+//! of every color, so this works on minimum bags as plain vectors of counts, one per color,
+//! and is not limited to red, green and blue.  `Game` itself only records red, green and
+//! blue, so `min_bags` and the `optimize` command deliberately stop at those three; a caller
+//! with more colors can build the vectors itself and call `pareto_frontier`.
+//!
+//! The cheapest bag admitting a set of games is the per-color maximum over their minimum bags,
+//! so each color's count only needs to be tried at values some game actually needs.  We choose
+//! a count for every color but the last, in increasing order, keeping only the games that
+//! still fit; for the last color the cheapest way to admit `c` of the remaining games is to
+//! take the `c` smallest.  Counts are only tried among games that are still in the running,
+//! and a branch is abandoned once it cannot beat the best bag already found for any number
+//! of games.  That relies on a bag's cost never going down as counts are chosen, so negative
+//! counts are rejected up front.  Costs are added up in an `i64`, which holds the sum of
+//! billions of `i32` counts.
+
+use crate::{min_bag_for_game, Game};
+
+/// A bag, how many cubes it holds, and how many games are possible with it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BagChoice {
+    pub bag: Vec<i32>,
+    pub cubes: i64,
+    pub games: usize,
+}
+
+/// Return the minimum bag of each game as a vector of red, green and blue counts, the only
+/// colors a `Game` has.
+pub fn min_bags(games: &[Game]) -> Vec<Vec<i32>> {
+    games
+        .iter()
+        .map(|g| {
+            let m = min_bag_for_game(g);
+            vec![m.r, m.g, m.b]
+        })
+        .collect()
+}
+
+/// Return the Pareto frontier of bag size against games admitted: for each size at which it
+/// goes up, the most games any bag of that many cubes admits, and such a bag.  The first
+/// entry is always the empty bag.  Every vector in `min_bags` must have the same length.
+/// Returns an error if a count is negative.
+pub fn pareto_frontier(min_bags: &[Vec<i32>]) -> Result<Vec<BagChoice>, String> {
+    let ncolors = min_bags.first().map_or(0, |b| b.len());
+    assert!(
+        min_bags.iter().all(|b| b.len() == ncolors),
+        "every bag needs the same number of colors"
+    );
+    if let Some(b) = min_bags.iter().find(|b| b.iter().any(|&n| n < 0)) {
+        return Err(format!("a minimum bag has a negative count: {:?}", b));
+    }
+    if ncolors == 0 {
+        // With no colors every game is possible with an empty bag.
+        return Ok(vec![BagChoice { bag: vec![], cubes: 0, games: min_bags.len() }]);
+    }
+    // best[c] is the cheapest bag found so far that admits c games.
+    let mut best: Vec<Option<BagChoice>> = vec![None; min_bags.len() + 1];
+    best[0] = Some(BagChoice { bag: vec![0; ncolors], cubes: 0, games: 0 });
+    let all: Vec<&[i32]> = min_bags.iter().map(|b| b.as_slice()).collect();
+    search(&all, &mut vec![], 0, &mut best);
+
+    // Admitting more games never costs fewer cubes, so keep each size's largest game count.
+    let mut frontier: Vec<BagChoice> = vec![];
+    for choice in best.into_iter().flatten().rev() {
+        if frontier.last().is_none_or(|f| choice.cubes < f.cubes) {
+            frontier.push(choice);
+        }
+    }
+    frontier.reverse();
+    Ok(frontier)
+}
+
+fn search(games: &[&[i32]], chosen: &mut Vec<i32>, cost: i64, best: &mut [Option<BagChoice>]) {
+    let color = chosen.len();
+    let ncolors = games.first().map_or(0, |g| g.len());
+    if color + 1 == ncolors {
+        let mut last: Vec<i32> = games.iter().map(|g| g[color]).collect();
+        last.sort();
+        for (i, &count) in last.iter().enumerate() {
+            let c = i + 1;
+            let cubes = cost + count as i64;
+            if best[c].as_ref().is_none_or(|b| cubes < b.cubes) {
+                let mut bag = chosen.clone();
+                bag.push(count);
+                best[c] = Some(BagChoice { bag, cubes, games: c });
+            }
+        }
+        return;
+    }
+    let mut counts: Vec<i32> = games.iter().map(|g| g[color]).collect();
+    counts.sort();
+    counts.dedup();
+    for count in counts {
+        let fits: Vec<&[i32]> = games.iter().filter(|g| g[color] <= count).copied().collect();
+        // Every bag below here costs at least this much; give up if that beats nothing.
+        let floor = cost + count as i64;
+        if best[1..=fits.len()].iter().all(|b| b.as_ref().is_some_and(|b| b.cubes <= floor)) {
+            continue;
+        }
+        chosen.push(count);
+        search(&fits, chosen, floor, best);
+        chosen.pop();
+    }
+}
+
+/// Return the bag of at most `cubes` cubes that makes the most games possible.  Returns an
+/// error if a count is negative.
+pub fn best_bag_for_budget(min_bags: &[Vec<i32>], cubes: i64) -> Result<BagChoice, String> {
+    Ok(pareto_frontier(min_bags)?
+        .into_iter()
+        .take_while(|c| c.cubes <= cubes)
+        .last()
+        .expect("the empty bag is always affordable"))
+}
+
+#[cfg(test)]
+fn example_min_bags() -> Vec<Vec<i32>> {
+    vec![
+        vec![4, 2, 6],
+        vec![1, 3, 4],
+        vec![20, 13, 6],
+        vec![14, 3, 15],
+        vec![6, 3, 2],
+    ]
+}
+
+#[test]
+fn test_pareto_frontier() {
+    let frontier: Vec<(i64, usize)> = pareto_frontier(&example_min_bags())
+        .unwrap()
+        .iter()
+        .map(|c| (c.cubes, c.games))
+        .collect();
+    assert_eq!(frontier, vec![(0, 0), (8, 1), (13, 2), (15, 3), (32, 4), (48, 5)]);
+}
+
+#[test]
+fn test_best_bag_for_budget() {
+    let bags = example_min_bags();
+    assert_eq!(best_bag_for_budget(&bags, 7).unwrap().games, 0);
+    assert_eq!(best_bag_for_budget(&bags, 14).unwrap().games, 2);
+    let c = best_bag_for_budget(&bags, 15);
+    assert_eq!(c, Ok(BagChoice { bag: vec![6, 3, 6], cubes: 15, games: 3 }));
+    assert_eq!(best_bag_for_budget(&bags, 1000).unwrap().games, 5);
+}
+
+#[test]
+fn test_pareto_frontier_big_and_negative_counts() {
+    let big = vec![vec![i32::MAX; 3]; 2];
+    let frontier = pareto_frontier(&big).unwrap();
+    assert_eq!(frontier.last().unwrap().cubes, 3 * i32::MAX as i64);
+    assert_eq!(best_bag_for_budget(&big, i64::MAX).unwrap().games, 2);
+    assert_eq!(
+        pareto_frontier(&[vec![1, 2, 3], vec![2, 0, -1]]),
+        Err("a minimum bag has a negative count: [2, 0, -1]".to_string())
+    );
+}
+
+#[test]
+fn test_pareto_frontier_matches_brute_force() {
+    // Small pseudo-random instances with four colors, checked against trying every bag.
+    let mut rand = crate::testgen::Rand::new(12345);
+    for _ in 0..20 {
+        let bags = rand.min_bags(8, 4, 5);
+        let mut brute = vec![i64::MAX; bags.len() + 1];
+        for a in 0..5 {
+            for b in 0..5 {
+                for c in 0..5 {
+                    for d in 0..5 {
+                        let bag = [a, b, c, d];
+                        let fits = |g: &&Vec<i32>| g.iter().zip(bag).all(|(x, y)| *x <= y);
+                        let n = bags.iter().filter(fits).count();
+                        brute[n] = brute[n].min((a + b + c + d) as i64);
+                    }
+                }
+            }
+        }
+        for n in (0..bags.len()).rev() {
+            brute[n] = brute[n].min(brute[n + 1]);
+        }
+        for choice in pareto_frontier(&bags).unwrap() {
+            assert_eq!(choice.cubes, brute[choice.games]);
+            assert_eq!(choice.bag.iter().sum::<i32>() as i64, choice.cubes);
+            let admitted = bags
+                .iter()
+                .filter(|g| g.iter().zip(&choice.bag).all(|(x, y)| x <= y))
+                .count();
+            assert!(admitted >= choice.games);
+        }
+    }
+}
+
+#[test]
+fn test_pareto_frontier_many_games() {
+    let bags = crate::testgen::Rand::new(99).min_bags(5000, 3, 20);
+    let frontier = pareto_frontier(&bags).unwrap();
+    assert_eq!(frontier.last().unwrap().games, 5000);
+    assert!(frontier.windows(2).all(|w| w[0].cubes < w[1].cubes && w[0].games < w[1].games));
+}
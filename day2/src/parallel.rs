@@ -0,0 +1,159 @@
+//! Answering both parts on several threads at once.
+//!
+//! The log is cut into one piece per thread at line boundaries.  Each thread adds up one part's
+//! answer for its piece, and the pieces are added up in order, so the result does not depend on
+//! how the threads were scheduled.  A piece with a bad line, or whose total takes the answer
+//! past `i32::MAX`, is read again starting from the answer so far, so the error is the one
+//! reading serially would give.
+
+use std::thread;
+
+use crate::{add_value, part1_value, part2_value, GameView};
+
+/// What a game adds to one part's answer.
+type Part = fn(&GameView) -> Result<i32, String>;
+
+/// The answers to both parts.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Answers {
+    pub part1: i32,
+    pub part2: i32,
+}
+
+/// Cut `input` into at most `n` pieces of about the same size, each ending with a newline
+/// except perhaps the last.  Returns each piece with the number of its first line.
+fn split_lines(input: &str, n: usize) -> Vec<(usize, &str)> {
+    let mut pieces = vec![];
+    let mut rest = input;
+    let mut line = 1;
+    let size = input.len().div_ceil(n.max(1));
+    while !rest.is_empty() {
+        let after = rest.as_bytes().get(size..).unwrap_or_default();
+        let end = match after.iter().position(|&b| b == b'\n') {
+            Some(i) => size + i + 1,
+            None => rest.len(),
+        };
+        let (piece, tail) = rest.split_at(end);
+        pieces.push((line, piece));
+        line += piece.matches('\n').count();
+        rest = tail;
+    }
+    pieces
+}
+
+/// Add what each game in `input` adds to `part` to `total`, with errors for the line they are
+/// on, counting from `first_line`.
+fn fold_piece(input: &str, first_line: usize, mut total: i32, part: Part) -> Result<i32, String> {
+    for (n, line) in input.split('\n').enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let add = |total| add_value(total, part(&GameView::parse(line)?)?);
+        total = add(total).map_err(|e: String| format!("line {}: {}", first_line + n, e))?;
+    }
+    Ok(total)
+}
+
+fn do_day2_parallel_with(input: &str, jobs: usize, part: Part) -> Result<i32, String> {
+    let pieces = split_lines(input, jobs);
+    let totals: Vec<Result<i32, String>> = thread::scope(|s| {
+        let handles: Vec<_> = pieces
+            .iter()
+            .map(|&(line, piece)| s.spawn(move || fold_piece(piece, line, 0, part)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let mut total: i32 = 0;
+    for (&(line, piece), r) in pieces.iter().zip(totals) {
+        total = match r.ok().and_then(|r| total.checked_add(r)) {
+            Some(total) => total,
+            None => fold_piece(piece, line, total, part)?,
+        };
+    }
+    Ok(total)
+}
+
+/// Return the part 1 answer, using `jobs` threads.
+pub fn do_day2_parallel_part1(input: &str, jobs: usize) -> Result<i32, String> {
+    do_day2_parallel_with(input, jobs, part1_value)
+}
+
+/// Return the part 2 answer, using `jobs` threads.
+pub fn do_day2_parallel_part2(input: &str, jobs: usize) -> Result<i32, String> {
+    do_day2_parallel_with(input, jobs, part2_value)
+}
+
+/// Return the answers to both parts, using `jobs` threads for each.
+pub fn do_day2_parallel(input: &str, jobs: usize) -> Result<Answers, String> {
+    Ok(Answers {
+        part1: do_day2_parallel_part1(input, jobs)?,
+        part2: do_day2_parallel_part2(input, jobs)?,
+    })
+}
+
+#[test]
+fn test_split_lines() {
+    let input = "a\nbb\nccc\ndddd\n";
+    let pieces = split_lines(input, 3);
+    assert_eq!(pieces, vec![(1, "a\nbb\nccc\n"), (4, "dddd\n")]);
+    assert_eq!(split_lines(input, 1), vec![(1, input)]);
+    assert_eq!(split_lines(input, 100).len(), 4);
+    assert_eq!(split_lines("x", 0), vec![(1, "x")]);
+    assert!(split_lines("", 4).is_empty());
+    assert_eq!(pieces.iter().map(|p| p.1).collect::<String>(), input);
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    let sim = crate::Simulation {
+        bag: vec![
+            ("red".to_string(), 15),
+            ("green".to_string(), 13),
+            ("blue".to_string(), 14),
+        ],
+        games: 500,
+        draws_per_game: 4,
+        seed: 3,
+    };
+    let log = crate::simulate(&sim).unwrap();
+    let serial = Answers {
+        part1: crate::do_day2_part1(&log).unwrap(),
+        part2: crate::do_day2_part2(&log).unwrap(),
+    };
+    for jobs in 1..=9 {
+        assert_eq!(do_day2_parallel(&log, jobs), Ok(serial));
+    }
+    assert_eq!(do_day2_parallel("", 4), Ok(Answers::default()));
+}
+
+#[test]
+fn test_parallel_reports_first_error() {
+    let mut log = "Game 1: 1 red\n".repeat(100);
+    log.push_str("Game 101: 1 puce\n");
+    log.push_str(&"Game 1: 1 red\n".repeat(100));
+    log.push_str("Game x: 1 red\n");
+    let serial = crate::do_day2_part1_from(log.as_bytes()).unwrap_err();
+    assert_eq!(serial, "line 101: unknown color: puce");
+    for jobs in 1..=8 {
+        assert_eq!(do_day2_parallel(&log, jobs), Err(serial.clone()));
+    }
+}
+
+#[test]
+fn test_parallel_overflow() {
+    // Each piece fits on its own, but not added together, or a game's power does not fit.
+    let mut log = "Game 2000000000: 1 red\n".repeat(4);
+    log.push_str("Game 5: 2147483647 red, 2147483647 green, 2147483647 blue\n");
+    let serial1 = crate::do_day2_part1(&log);
+    let serial2 = crate::do_day2_part2(&log);
+    assert_eq!(serial1, Err("line 2: overflow".to_string()));
+    assert_eq!(serial2, Err("line 5: overflow".to_string()));
+    for jobs in 1..=6 {
+        assert_eq!(do_day2_parallel_part1(&log, jobs), serial1, "{} jobs", jobs);
+        assert_eq!(do_day2_parallel_part2(&log, jobs), serial2, "{} jobs", jobs);
+    }
+    // Part 1 does not depend on the power that overflows part 2.
+    let log = "Game 1: 2147483647 red, 2147483647 green, 2147483647 blue\n".repeat(3);
+    assert_eq!(do_day2_parallel_part1(&log, 2), Ok(0));
+    assert_eq!(do_day2_parallel(&log, 2), Err("line 1: overflow".to_string()));
+}
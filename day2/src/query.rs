@@ -0,0 +1,550 @@
+//! A small query language over games.
+//!
+//! ```text
+//! query      = select [ "where" condition ]
+//! select     = "games" | "count" | ( "sum" | "min" | "max" ) value
+//! condition  = and { "or" and }
+//! and        = not { "and" not }
+//! not        = "not" not | "(" condition ")" | value compare value
+//! compare    = "=" | "!=" | "<" | "<=" | ">" | ">="
+//! value      = term { ( "+" | "-" ) term }
+//! term       = atom { "*" atom }
+//! atom       = NUMBER | "id" | "draws" | "power" | "cubes" | COLOR
+//!            | ( "max" | "min" | "sum" ) "(" COLOR ")"
+//! ```
+//!
+//! A value belongs to one game.  A bare color is the most cubes of it in any draw, the same as
+//! `max(color)` and as the game's minimum bag; `min` and `sum` are over the draws.  `power`
+//! and `cubes` are the product and the sum of the minimum bag.  So
+//! `games where max(red) > 10 and draws >= 4` lists game ids, and `sum power where blue < 5`
+//! adds up part 2's powers for some of the games.
+
+use std::fmt;
+
+use crate::{min_bag_for_game, Color, Game};
+
+/// How to combine a color's counts over draws, or a value over games.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Reduce {
+    Max,
+    Min,
+    Sum,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A number worked out for each game.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Value {
+    Number(i64),
+    Id,
+    Draws,
+    Power,
+    Cubes,
+    /// A color's count reduced over every draw of the game.
+    Color(Reduce, Color),
+    Arith(Box<Value>, ArithOp, Box<Value>),
+}
+
+/// Whether a game matches.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Condition {
+    Compare(Value, CmpOp, Value),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+/// What to report about the matching games.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Select {
+    Games,
+    Count,
+    Aggregate(Reduce, Value),
+}
+
+/// A parsed query.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Query {
+    pub select: Select,
+    pub filter: Option<Condition>,
+}
+
+/// The answer to a query.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum QueryResult {
+    /// The ids of the matching games, in log order.
+    Games(Vec<i32>),
+    /// A count or aggregate.  `None` is the minimum or maximum of no games.
+    Number(Option<i64>),
+}
+
+/// One id per line, or the number, or `none`.
+impl fmt::Display for QueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryResult::Games(ids) => ids.iter().try_for_each(|id| writeln!(f, "{}", id)),
+            QueryResult::Number(Some(n)) => writeln!(f, "{}", n),
+            QueryResult::Number(None) => writeln!(f, "none"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum Token {
+    Number(i64),
+    Word(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "`{}`", n),
+            Token::Word(w) => write!(f, "`{}`", w),
+            Token::Symbol(s) => write!(f, "`{}`", s),
+            Token::End => write!(f, "end of query"),
+        }
+    }
+}
+
+/// Longest first, so that `<=` is not read as `<` then `=`.
+const SYMBOLS: [&str; 11] = ["<=", ">=", "!=", "==", "<", ">", "=", "(", ")", "+", "-"];
+
+/// Split a query into tokens, each with the column it starts at, from 1.
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        let col = s[..i].chars().count() + 1;
+        let rest = &s[i..];
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let len = if c.is_ascii_digit() {
+            let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let n = rest[..len]
+                .parse()
+                .map_err(|e| format!("at column {}: bad number: {}", col, e))?;
+            tokens.push((col, Token::Number(n)));
+            len
+        } else if c.is_alphabetic() {
+            let len = rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len());
+            tokens.push((col, Token::Word(rest[..len].to_lowercase())));
+            len
+        } else if c == '*' {
+            tokens.push((col, Token::Symbol("*")));
+            1
+        } else if let Some(sym) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            tokens.push((col, Token::Symbol(sym)));
+            sym.len()
+        } else {
+            return Err(format!("at column {}: unexpected `{}`", col, c));
+        };
+        while chars.peek().is_some_and(|&(j, _)| j < i + len) {
+            chars.next();
+        }
+    }
+    tokens.push((s.chars().count() + 1, Token::End));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].1
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].1.clone();
+        if t != Token::End {
+            self.pos += 1;
+        }
+        t
+    }
+
+    /// Return an error about the next token.
+    fn error<T>(&self, expected: &str) -> Result<T, String> {
+        let (col, t) = &self.tokens[self.pos];
+        Err(format!("at column {}: expected {}, found {}", col, expected, t))
+    }
+
+    /// Skip the next token if it is the word or symbol `s`.
+    fn eat(&mut self, s: &str) -> bool {
+        let found = match self.peek() {
+            Token::Word(w) => w == s,
+            Token::Symbol(sym) => *sym == s,
+            _ => false,
+        };
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), String> {
+        if self.eat(s) {
+            Ok(())
+        } else {
+            self.error(&format!("`{}`", s))
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, String> {
+        let select = if self.eat("games") {
+            Select::Games
+        } else if self.eat("count") {
+            Select::Count
+        } else if let Some(r) = self.reduce() {
+            Select::Aggregate(r, self.value()?)
+        } else {
+            return self.error("`games`, `count`, `sum`, `min` or `max`");
+        };
+        let filter = if self.eat("where") { Some(self.condition()?) } else { None };
+        if *self.peek() != Token::End {
+            return self.error(if filter.is_some() { "`and` or `or`" } else { "`where`" });
+        }
+        Ok(Query { select, filter })
+    }
+
+    fn reduce(&mut self) -> Option<Reduce> {
+        if self.eat("max") {
+            Some(Reduce::Max)
+        } else if self.eat("min") {
+            Some(Reduce::Min)
+        } else if self.eat("sum") {
+            Some(Reduce::Sum)
+        } else {
+            None
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let mut c = self.and()?;
+        while self.eat("or") {
+            c = Condition::Or(Box::new(c), Box::new(self.and()?));
+        }
+        Ok(c)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut c = self.not()?;
+        while self.eat("and") {
+            c = Condition::And(Box::new(c), Box::new(self.not()?));
+        }
+        Ok(c)
+    }
+
+    fn not(&mut self) -> Result<Condition, String> {
+        if self.eat("not") {
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        if self.eat("(") {
+            let c = self.condition()?;
+            self.expect(")")?;
+            return Ok(c);
+        }
+        let left = self.value()?;
+        let op = match self.peek() {
+            Token::Symbol("=") | Token::Symbol("==") => CmpOp::Eq,
+            Token::Symbol("!=") => CmpOp::Ne,
+            Token::Symbol("<") => CmpOp::Lt,
+            Token::Symbol("<=") => CmpOp::Le,
+            Token::Symbol(">") => CmpOp::Gt,
+            Token::Symbol(">=") => CmpOp::Ge,
+            _ => return self.error("a comparison"),
+        };
+        self.pos += 1;
+        Ok(Condition::Compare(left, op, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        let mut v = self.term()?;
+        loop {
+            let op = if self.eat("+") {
+                ArithOp::Add
+            } else if self.eat("-") {
+                ArithOp::Sub
+            } else {
+                return Ok(v);
+            };
+            v = Value::Arith(Box::new(v), op, Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let mut v = self.atom()?;
+        while self.eat("*") {
+            v = Value::Arith(Box::new(v), ArithOp::Mul, Box::new(self.atom()?));
+        }
+        Ok(v)
+    }
+
+    fn atom(&mut self) -> Result<Value, String> {
+        if let Some(r) = self.reduce() {
+            self.expect("(")?;
+            let c = self.color().map_or_else(|| self.error("a color"), Ok)?;
+            self.expect(")")?;
+            return Ok(Value::Color(r, c));
+        }
+        if let Some(c) = self.color() {
+            return Ok(Value::Color(Reduce::Max, c));
+        }
+        let v = match self.peek() {
+            Token::Number(n) => Value::Number(*n),
+            Token::Word(w) if w == "id" => Value::Id,
+            Token::Word(w) if w == "draws" => Value::Draws,
+            Token::Word(w) if w == "power" => Value::Power,
+            Token::Word(w) if w == "cubes" => Value::Cubes,
+            _ => return self.error("a number, color or game property"),
+        };
+        self.next();
+        Ok(v)
+    }
+
+    fn color(&mut self) -> Option<Color> {
+        let c = Color::ALL.into_iter().find(|c| self.eat(c.name()))?;
+        Some(c)
+    }
+}
+
+/// Parse a query, or say where it went wrong.
+pub fn parse_query(s: &str) -> Result<Query, String> {
+    Parser { tokens: tokenize(s)?, pos: 0 }.query()
+}
+
+/// Apply `op` to `a` and `b`, or say which game it overflowed in.
+fn checked(game: &Game, a: i64, op: ArithOp, b: i64) -> Result<i64, String> {
+    let (res, sign) = match op {
+        ArithOp::Add => (a.checked_add(b), '+'),
+        ArithOp::Sub => (a.checked_sub(b), '-'),
+        ArithOp::Mul => (a.checked_mul(b), '*'),
+    };
+    res.ok_or_else(|| format!("overflow in game {}: {} {} {}", game.id, a, sign, b))
+}
+
+impl Value {
+    /// Work out the value for a game, or say where it overflowed.
+    fn eval(&self, game: &Game) -> Result<i64, String> {
+        Ok(match self {
+            Value::Number(n) => *n,
+            Value::Id => game.id as i64,
+            Value::Draws => game.draws.len() as i64,
+            Value::Power => {
+                let m = min_bag_for_game(game);
+                let rg = checked(game, m.r as i64, ArithOp::Mul, m.g as i64)?;
+                checked(game, rg, ArithOp::Mul, m.b as i64)?
+            }
+            Value::Cubes => {
+                let m = min_bag_for_game(game);
+                let rg = checked(game, m.r as i64, ArithOp::Add, m.g as i64)?;
+                checked(game, rg, ArithOp::Add, m.b as i64)?
+            }
+            Value::Color(r, c) => {
+                let mut counts = game.draws.iter().map(|d| d.get(*c) as i64);
+                match r {
+                    Reduce::Max => counts.max().unwrap_or(0).max(0),
+                    Reduce::Min => counts.min().unwrap_or(0),
+                    Reduce::Sum => counts.try_fold(0, |a, b| checked(game, a, ArithOp::Add, b))?,
+                }
+            }
+            Value::Arith(a, op, b) => checked(game, a.eval(game)?, *op, b.eval(game)?)?,
+        })
+    }
+}
+
+impl Condition {
+    fn matches(&self, game: &Game) -> Result<bool, String> {
+        Ok(match self {
+            Condition::Compare(a, op, b) => {
+                let (a, b) = (a.eval(game)?, b.eval(game)?);
+                match op {
+                    CmpOp::Eq => a == b,
+                    CmpOp::Ne => a != b,
+                    CmpOp::Lt => a < b,
+                    CmpOp::Le => a <= b,
+                    CmpOp::Gt => a > b,
+                    CmpOp::Ge => a >= b,
+                }
+            }
+            Condition::And(a, b) => a.matches(game)? && b.matches(game)?,
+            Condition::Or(a, b) => a.matches(game)? || b.matches(game)?,
+            Condition::Not(c) => !c.matches(game)?,
+        })
+    }
+}
+
+impl Query {
+    /// Answer the query over `games`, or say where the arithmetic overflowed.
+    pub fn run(&self, games: &[Game]) -> Result<QueryResult, String> {
+        let mut matching = vec![];
+        for g in games {
+            if self.filter.as_ref().map_or(Ok(true), |c| c.matches(g))? {
+                matching.push(g);
+            }
+        }
+        Ok(match &self.select {
+            Select::Games => QueryResult::Games(matching.iter().map(|g| g.id).collect()),
+            Select::Count => QueryResult::Number(Some(matching.len() as i64)),
+            Select::Aggregate(r, v) => {
+                let values = matching.iter().map(|g| v.eval(g)).collect::<Result<Vec<_>, _>>()?;
+                QueryResult::Number(match r {
+                    Reduce::Max => values.into_iter().max(),
+                    Reduce::Min => values.into_iter().min(),
+                    Reduce::Sum => Some(
+                        values
+                            .into_iter()
+                            .try_fold(0i64, |total, v| total.checked_add(v))
+                            .ok_or("overflow in the sum")?,
+                    ),
+                })
+            }
+        })
+    }
+}
+
+/// Parse a query and answer it over `games`.
+pub fn query(games: &[Game], s: &str) -> Result<QueryResult, String> {
+    parse_query(s)?.run(games)
+}
+
+#[cfg(test)]
+fn example_games() -> Vec<Game> {
+    crate::parse_games(
+        "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green; 1 red, 1 blue, 1 green; 1 red
+",
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_parse_query() {
+    use Value::*;
+    assert_eq!(
+        parse_query("games where max(red) > 10 and draws >= 4"),
+        Ok(Query {
+            select: Select::Games,
+            filter: Some(Condition::And(
+                Box::new(Condition::Compare(
+                    Color(Reduce::Max, crate::Color::Red),
+                    CmpOp::Gt,
+                    Number(10)
+                )),
+                Box::new(Condition::Compare(Draws, CmpOp::Ge, Number(4))),
+            )),
+        })
+    );
+    // `*` binds tighter than `+`, and `and` tighter than `or`.
+    assert_eq!(
+        parse_query("sum id + 2 * draws"),
+        Ok(Query {
+            select: Select::Aggregate(
+                Reduce::Sum,
+                Arith(
+                    Box::new(Id),
+                    ArithOp::Add,
+                    Box::new(Arith(Box::new(Number(2)), ArithOp::Mul, Box::new(Draws)))
+                ),
+            ),
+            filter: None,
+        })
+    );
+    let q = parse_query("count where id = 1 or id = 2 and id = 3").unwrap();
+    assert!(matches!(q.filter, Some(Condition::Or(..))));
+    assert_eq!(parse_query("GAMES where RED < 5"), parse_query("games where red < 5"));
+}
+
+#[test]
+fn test_parse_query_errors() {
+    let err = |s| parse_query(s).unwrap_err();
+    assert_eq!(
+        err("list games"),
+        "at column 1: expected `games`, `count`, `sum`, `min` or `max`, found `list`"
+    );
+    assert_eq!(err("games where red"), "at column 16: expected a comparison, found end of query");
+    assert_eq!(
+        err("games where max(purple) > 1"),
+        "at column 17: expected a color, found `purple`"
+    );
+    assert_eq!(err("games where (red > 1"), "at column 21: expected `)`, found end of query");
+    assert_eq!(err("games red > 1"), "at column 7: expected `where`, found `red`");
+    assert_eq!(
+        err("games where red > 1 blue"),
+        "at column 21: expected `and` or `or`, found `blue`"
+    );
+    assert_eq!(err("games where red > 1 & blue > 1"), "at column 21: unexpected `&`");
+    assert_eq!(
+        err("sum where"),
+        "at column 5: expected a number, color or game property, found `where`"
+    );
+}
+
+#[test]
+fn test_query() {
+    let games = example_games();
+    let q = |s: &str| query(&games, s).unwrap();
+    assert_eq!(q("games"), QueryResult::Games(vec![1, 2, 3, 4, 5]));
+    assert_eq!(q("games where max(red) > 10 and draws >= 3"), QueryResult::Games(vec![3, 4]));
+    assert_eq!(q("games where draws >= 4"), QueryResult::Games(vec![5]));
+    assert_eq!(q("sum power"), QueryResult::Number(Some(2286)));
+    assert_eq!(q("sum power where blue < 5"), QueryResult::Number(Some(12 + 36)));
+    // Part 1, asked both ways round.
+    let possible = "red <= 12 and green <= 13 and blue <= 14";
+    assert_eq!(q(&format!("sum id where {}", possible)), QueryResult::Number(Some(1 + 2 + 5)));
+    assert_eq!(q(&format!("count where not ({})", possible)), QueryResult::Number(Some(2)));
+    assert_eq!(q("max cubes"), QueryResult::Number(Some(39)));
+    assert_eq!(q("min min(green) where id != 4"), QueryResult::Number(Some(0)));
+    assert_eq!(q("sum sum(red) - 1 where id = 5"), QueryResult::Number(Some(8)));
+    assert_eq!(q("max id where id > 5"), QueryResult::Number(None));
+    assert_eq!(q("games where red > 100").to_string(), "");
+    assert_eq!(q("count where red > 100").to_string(), "0\n");
+}
+
+#[test]
+fn test_query_overflow() {
+    let games = example_games();
+    assert_eq!(
+        query(&games, "sum 9999999999 * 9999999999").unwrap_err(),
+        "overflow in game 1: 9999999999 * 9999999999"
+    );
+    assert_eq!(
+        query(&games, "games where 0 - 9223372036854775807 - 2 < 0").unwrap_err(),
+        "overflow in game 1: -9223372036854775807 - 2"
+    );
+    assert_eq!(query(&games, "sum 4611686018427387904").unwrap_err(), "overflow in the sum");
+    let big = crate::parse_games("Game 1: 2147483647 red, 2147483647 green, 2147483647 blue")
+        .unwrap();
+    assert_eq!(
+        query(&big, "sum power").unwrap_err(),
+        "overflow in game 1: 4611686014132420609 * 2147483647"
+    );
+    assert_eq!(query(&big, "sum cubes").unwrap().to_string(), "6442450941\n");
+    // Conditions short-circuit, so an overflow they never reach is not an error.
+    assert!(query(&games, "games where id > 5 and 9999999999 * 9999999999 > 0").is_ok());
+}
@@ -0,0 +1,163 @@
+//! Playing the cube game to generate logs with a known bag.
+//!
+//! Each draw takes a random number of cubes, at least one, from the bag without replacement,
+//! and they go back in before the next draw.  The same seed always gives the same log.
+
+/// The parameters of a simulation.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Simulation {
+    /// Each color in the bag and how many cubes of it there are.  Colors other than red, green
+    /// and blue are written to the log, but `parseline` will not read them back.
+    pub bag: Vec<(String, i32)>,
+    pub games: usize,
+    pub draws_per_game: usize,
+    pub seed: u64,
+}
+
+/// SplitMix64: small, fast and good enough for shuffling cubes.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Return a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Return a game log in the same format as the puzzle input, one line per game.
+/// Returns an error if the bag is empty or has a negative count, if a color's name would not
+/// read back as one word, or if there are no draws per game, since `Game 1: ` reads back as
+/// one empty draw rather than none.
+pub fn simulate(sim: &Simulation) -> Result<String, String> {
+    if sim.draws_per_game == 0 {
+        return Err("each game needs at least one draw".to_string());
+    }
+    // The log separates counts from colors with spaces, and draws and games with `,;:`.
+    let separator = |ch: char| ch.is_whitespace() || ",;:".contains(ch);
+    if let Some((color, _)) = sim.bag.iter().find(|(c, _)| c.is_empty() || c.contains(separator)) {
+        return Err(format!("{:?} cannot be a color: it would not read back", color));
+    }
+    if let Some((color, n)) = sim.bag.iter().find(|(_, n)| *n < 0) {
+        return Err(format!("cannot have {} {} cubes", n, color));
+    }
+    // One entry per cube, holding the index of its color.
+    let mut cubes: Vec<usize> = vec![];
+    for (i, (_, n)) in sim.bag.iter().enumerate() {
+        cubes.extend(std::iter::repeat_n(i, *n as usize));
+    }
+    if cubes.is_empty() {
+        return Err("cannot draw from an empty bag".to_string());
+    }
+
+    let mut rng = Rng(sim.seed);
+    let mut log = String::new();
+    for id in 1..=sim.games {
+        let mut draws = vec![];
+        for _ in 0..sim.draws_per_game {
+            let size = 1 + rng.below(cubes.len());
+            // Partial Fisher-Yates shuffle: the first `size` cubes are the draw.
+            for i in 0..size {
+                let j = i + rng.below(cubes.len() - i);
+                cubes.swap(i, j);
+            }
+            let mut counts = vec![0; sim.bag.len()];
+            for &c in &cubes[..size] {
+                counts[c] += 1;
+            }
+            let parts: Vec<String> = counts
+                .iter()
+                .zip(&sim.bag)
+                .filter(|(n, _)| **n > 0)
+                .map(|(n, (color, _))| format!("{} {}", n, color))
+                .collect();
+            draws.push(parts.join(", "));
+        }
+        log.push_str(&format!("Game {}: {}\n", id, draws.join("; ")));
+    }
+    Ok(log)
+}
+
+#[cfg(test)]
+fn rgb(r: i32, g: i32, b: i32, games: usize, seed: u64) -> Simulation {
+    Simulation {
+        bag: vec![
+            ("red".to_string(), r),
+            ("green".to_string(), g),
+            ("blue".to_string(), b),
+        ],
+        games,
+        draws_per_game: 4,
+        seed,
+    }
+}
+
+#[test]
+fn test_simulate_is_deterministic() {
+    let sim = rgb(3, 2, 1, 10, 42);
+    assert_eq!(simulate(&sim), simulate(&sim));
+    assert_ne!(simulate(&sim), simulate(&Simulation { seed: 43, ..sim }));
+}
+
+#[test]
+fn test_simulate_parses_strictly() {
+    let log = simulate(&rgb(12, 13, 14, 50, 7)).unwrap();
+    let report = crate::parse_games_with(&log, crate::Validation::Strict).unwrap();
+    assert_eq!(report.games.len(), 50);
+    assert!(report.games.iter().all(|g| g.draws.len() == 4));
+}
+
+#[test]
+fn test_simulate_against_parts() {
+    // Every game drawn from the part 1 bag is possible with it, and never needs more.
+    let log = simulate(&rgb(12, 13, 14, 100, 1)).unwrap();
+    assert_eq!(crate::do_day2_part1(&log), Ok((1..=100).sum::<i32>()));
+    assert!(crate::do_day2_part2(&log).unwrap() <= 100 * 12 * 13 * 14);
+    for g in crate::parse_games(&log).unwrap() {
+        let m = crate::min_bag_for_game(&g);
+        assert!(m.r <= 12 && m.g <= 13 && m.b <= 14);
+    }
+    // Drawing from a bag with more red cubes than part 1's makes some games impossible.
+    let log = simulate(&rgb(20, 13, 14, 100, 1)).unwrap();
+    assert!(crate::do_day2_part1(&log).unwrap() < (1..=100).sum::<i32>());
+}
+
+#[test]
+fn test_simulate_against_infer_bag() {
+    let log = simulate(&rgb(5, 3, 4, 50, 9)).unwrap();
+    let games = crate::parse_games(&log).unwrap();
+    let e = crate::infer_bag(&games, 30).unwrap();
+    assert!(e.low.r <= 5 && 5 <= e.high.r);
+    assert!(e.low.g <= 3 && 3 <= e.high.g);
+    assert!(e.low.b <= 4 && 4 <= e.high.b);
+}
+
+#[test]
+fn test_simulate_other_colors() {
+    let sim = Simulation {
+        bag: vec![("purple".to_string(), 2), ("red".to_string(), 0)],
+        games: 2,
+        draws_per_game: 2,
+        seed: 0,
+    };
+    let log = simulate(&sim).unwrap();
+    assert!(log.lines().all(|l| l.contains("purple") && !l.contains("red")));
+    assert!(simulate(&Simulation { bag: vec![], ..sim.clone() }).is_err());
+    assert!(simulate(&Simulation { bag: vec![("red".to_string(), -1)], ..sim.clone() }).is_err());
+    assert_eq!(
+        simulate(&Simulation { draws_per_game: 0, ..sim.clone() }),
+        Err("each game needs at least one draw".to_string())
+    );
+    for name in ["", "dark red", "red,", "red;", "red:", "red\t"] {
+        let sim = Simulation { bag: vec![(name.to_string(), 1)], ..sim.clone() };
+        let e = format!("{:?} cannot be a color: it would not read back", name);
+        assert_eq!(simulate(&sim), Err(e));
+    }
+}
@@ -0,0 +1,259 @@
+//! Descriptive statistics over a game log.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{min_bag_for_game, Color, ColorCount, Game};
+
+/// How many of the games with the largest minimum bags to list.
+const LARGEST_BAGS: usize = 5;
+
+/// The spread of a list of numbers.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Summary {
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    pub median: f64,
+    /// How many times each value occurs.
+    pub histogram: BTreeMap<i64, usize>,
+}
+
+impl Summary {
+    /// Summarize `values`.  An empty list has all zeros.
+    pub fn of(values: &[i64]) -> Summary {
+        let mut sorted = values.to_vec();
+        sorted.sort();
+        let mut histogram = BTreeMap::new();
+        for &v in &sorted {
+            *histogram.entry(v).or_insert(0) += 1;
+        }
+        let n = sorted.len();
+        if n == 0 {
+            return Summary { min: 0, max: 0, mean: 0.0, median: 0.0, histogram };
+        }
+        // Add up in an `i128`, which the sum of any list that fits in memory fits in.
+        let median = if n % 2 == 1 {
+            sorted[n / 2] as f64
+        } else {
+            (sorted[n / 2 - 1] as i128 + sorted[n / 2] as i128) as f64 / 2.0
+        };
+        Summary {
+            min: sorted[0],
+            max: sorted[n - 1],
+            mean: sorted.iter().map(|&v| v as i128).sum::<i128>() as f64 / n as f64,
+            median,
+            histogram,
+        }
+    }
+}
+
+/// The cubes of one color taken in each draw.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct ColorStats {
+    pub color: Color,
+    pub per_draw: Summary,
+}
+
+/// A game and its minimum bag.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct GameBag {
+    pub id: i32,
+    pub bag: ColorCount,
+    pub cubes: i64,
+}
+
+/// Pearson correlation between the counts of two colors across all draws, or `None` if either
+/// count never varies.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Correlation {
+    pub a: Color,
+    pub b: Color,
+    pub r: Option<f64>,
+}
+
+/// Statistics about a game log.
+#[derive(PartialEq, Debug, Clone, Serialize)]
+pub struct Stats {
+    pub games: usize,
+    pub draws: usize,
+    pub colors: Vec<ColorStats>,
+    pub draws_per_game: Summary,
+    /// The games whose minimum bags hold the most cubes, largest first.
+    pub largest_min_bags: Vec<GameBag>,
+    /// The power of each game's minimum bag, as summed by part 2.
+    pub power: Summary,
+    pub correlations: Vec<Correlation>,
+}
+
+fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    let mx = xs.iter().sum::<f64>() / n;
+    let my = ys.iter().sum::<f64>() / n;
+    let cov: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    let vx: f64 = xs.iter().map(|x| (x - mx) * (x - mx)).sum();
+    let vy: f64 = ys.iter().map(|y| (y - my) * (y - my)).sum();
+    if vx == 0.0 || vy == 0.0 {
+        return None;
+    }
+    Some(cov / (vx * vy).sqrt())
+}
+
+/// Return statistics about `games`, or say which game's minimum bag has too many cubes to add
+/// up or multiply in an `i64`.
+pub fn stats(games: &[Game]) -> Result<Stats, String> {
+    let draws: Vec<&ColorCount> = games.iter().flat_map(|g| &g.draws).collect();
+    let colors = Color::ALL
+        .iter()
+        .map(|&color| ColorStats {
+            color,
+            per_draw: Summary::of(&draws.iter().map(|d| d.get(color) as i64).collect::<Vec<_>>()),
+        })
+        .collect();
+    let draws_per_game =
+        Summary::of(&games.iter().map(|g| g.draws.len() as i64).collect::<Vec<_>>());
+
+    let mut bags = vec![];
+    let mut powers = vec![];
+    for g in games {
+        let bag = min_bag_for_game(g);
+        let (r, gr, b) = (bag.r as i64, bag.g as i64, bag.b as i64);
+        let overflow =
+            |a: i64, op: char, b: i64| format!("overflow in game {}: {} {} {}", g.id, a, op, b);
+        let cubes = r.checked_add(gr).ok_or_else(|| overflow(r, '+', gr))?;
+        let cubes = cubes.checked_add(b).ok_or_else(|| overflow(cubes, '+', b))?;
+        let power = r.checked_mul(gr).ok_or_else(|| overflow(r, '*', gr))?;
+        powers.push(power.checked_mul(b).ok_or_else(|| overflow(power, '*', b))?);
+        bags.push(GameBag { id: g.id, bag, cubes });
+    }
+    let power = Summary::of(&powers);
+    // Stable, so ties stay in game order.
+    bags.sort_by_key(|b| std::cmp::Reverse(b.cubes));
+    bags.truncate(LARGEST_BAGS);
+
+    let counts = |c: Color| draws.iter().map(|d| d.get(c) as f64).collect::<Vec<_>>();
+    let mut correlations = vec![];
+    for (i, &a) in Color::ALL.iter().enumerate() {
+        for &b in &Color::ALL[i + 1..] {
+            correlations.push(Correlation { a, b, r: correlation(&counts(a), &counts(b)) });
+        }
+    }
+
+    Ok(Stats {
+        games: games.len(),
+        draws: draws.len(),
+        colors,
+        draws_per_game,
+        largest_min_bags: bags,
+        power,
+        correlations,
+    })
+}
+
+impl Stats {
+    /// Return the statistics as JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("stats should always serialize")
+    }
+}
+
+/// A human-readable table.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} games, {} draws", self.games, self.draws)?;
+        writeln!(f, "{:<16}{:>8}{:>8}{:>8}{:>8}", "", "min", "max", "mean", "median")?;
+        let row = |f: &mut fmt::Formatter, label: &str, s: &Summary| {
+            writeln!(f, "{:<16}{:>8}{:>8}{:>8.2}{:>8.1}", label, s.min, s.max, s.mean, s.median)
+        };
+        for c in &self.colors {
+            row(f, &format!("{} per draw", c.color.name()), &c.per_draw)?;
+        }
+        row(f, "draws per game", &self.draws_per_game)?;
+        row(f, "power", &self.power)?;
+        writeln!(f, "Largest minimum bags:")?;
+        for b in &self.largest_min_bags {
+            writeln!(
+                f,
+                "  Game {}: {} red, {} green, {} blue ({} cubes)",
+                b.id, b.bag.r, b.bag.g, b.bag.b, b.cubes
+            )?;
+        }
+        writeln!(f, "Correlation between colors in a draw:")?;
+        for c in &self.correlations {
+            let label = format!("{}/{}", c.a.name(), c.b.name());
+            match c.r {
+                Some(r) => writeln!(f, "  {:<12}{:>6.2}", label, r)?,
+                None => writeln!(f, "  {:<12}{:>6}", label, "-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_summary() {
+    let s = Summary::of(&[3, 1, 2, 2]);
+    assert_eq!((s.min, s.max, s.mean, s.median), (1, 3, 2.0, 2.0));
+    assert_eq!(s.histogram, BTreeMap::from([(1, 1), (2, 2), (3, 1)]));
+    assert_eq!(Summary::of(&[5, 1]).median, 3.0);
+    assert_eq!(Summary::of(&[]).max, 0);
+    assert_eq!(Summary::of(&[i64::MAX, i64::MAX]).mean, i64::MAX as f64);
+    assert_eq!(Summary::of(&[i64::MAX, i64::MAX]).median, i64::MAX as f64);
+}
+
+#[test]
+fn test_correlation() {
+    assert_eq!(correlation(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]), Some(1.0));
+    assert_eq!(correlation(&[1.0, 2.0, 3.0], &[3.0, 2.0, 1.0]), Some(-1.0));
+    assert_eq!(correlation(&[1.0, 2.0, 3.0], &[5.0, 5.0, 5.0]), None);
+}
+
+#[test]
+fn test_stats() {
+    let games = crate::parse_games(
+        "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green
+",
+    )
+    .unwrap();
+    let s = stats(&games).unwrap();
+    assert_eq!((s.games, s.draws), (5, 14));
+    assert_eq!(s.colors[0].color, Color::Red);
+    assert_eq!(s.colors[0].per_draw.max, 20);
+    assert_eq!(s.draws_per_game.histogram, BTreeMap::from([(2, 1), (3, 4)]));
+    assert_eq!(s.power.histogram.keys().sum::<i64>(), 2286);
+    assert_eq!(
+        s.largest_min_bags.iter().map(|b| (b.id, b.cubes)).collect::<Vec<_>>(),
+        vec![(3, 39), (4, 32), (1, 12), (5, 11), (2, 8)]
+    );
+    assert_eq!(s.correlations.len(), 3);
+
+    let json: serde_json::Value = serde_json::from_str(&s.to_json()).unwrap();
+    assert_eq!(json["games"], 5);
+    assert_eq!(json["colors"][2]["color"], "blue");
+    assert_eq!(json["largest_min_bags"][0]["bag"]["r"], 20);
+    assert_eq!(json["draws_per_game"]["histogram"]["3"], 4);
+
+    let table = s.to_string();
+    assert!(table.starts_with("5 games, 14 draws\n"));
+    assert!(table.contains("Game 3: 20 red, 13 green, 6 blue (39 cubes)"));
+}
+
+#[test]
+fn test_stats_overflow() {
+    // Three i32 counts add up in an i64, but their product does not fit.
+    let games =
+        crate::parse_games("Game 1: 2147483647 red, 2147483647 green, 2147483647 blue").unwrap();
+    assert_eq!(
+        stats(&games).unwrap_err(),
+        "overflow in game 1: 4611686014132420609 * 2147483647"
+    );
+    let s = stats(&crate::parse_games("Game 1: 2147483647 red, 2147483647 green").unwrap());
+    assert_eq!(s.unwrap().largest_min_bags[0].cubes, 2 * 2147483647);
+}
@@ -0,0 +1,23 @@
+//! Pseudo-random minimum bags for tests.
+//!
+//! A linear congruential generator needs no dependencies and gives the same bags on every run.
+
+/// A linear congruential generator.
+pub struct Rand(u64);
+
+impl Rand {
+    pub fn new(seed: u64) -> Rand {
+        Rand(seed)
+    }
+
+    /// Return a number in `0..n`.
+    pub fn below(&mut self, n: i32) -> i32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % n as u64) as i32
+    }
+
+    /// Return a minimum bag for each of `games` games, with each of `colors` counts in `0..n`.
+    pub fn min_bags(&mut self, games: usize, colors: usize, n: i32) -> Vec<Vec<i32>> {
+        (0..games).map(|_| (0..colors).map(|_| self.below(n)).collect()).collect()
+    }
+}
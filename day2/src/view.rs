@@ -0,0 +1,203 @@
+//! Parsing game lines in place, without allocating.
+//!
+//! A `GameView` borrows its line and reads the draws only when asked, so a whole log can be
+//! answered in constant memory: `game_views` walks a `&str`, and `fold_games` reuses one line
+//! buffer while reading from a `BufRead`.
+
+use std::io::BufRead;
+
+use crate::{ColorCount, Game};
+
+/// A game line that has had its id read, but not its draws.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct GameView<'a> {
+    pub id: i32,
+    draws: &'a str,
+}
+
+/// The text of one draw, between semicolons.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct DrawView<'a>(&'a str);
+
+impl<'a> GameView<'a> {
+    /// Read the `Game N:` header of a line.
+    pub fn parse(line: &'a str) -> Result<GameView<'a>, String> {
+        let (start, draws) = line
+            .split_once(':')
+            .ok_or_else(|| format!("no colon in line: {}", line))?;
+        let id = start
+            .strip_prefix("Game ")
+            .ok_or_else(|| format!("line does not start with `Game `: {}", line))?
+            .parse::<i32>()
+            .map_err(|e| format!("bad game id in {}: {}", start, e))?;
+        Ok(GameView { id, draws })
+    }
+
+    pub fn draws(&self) -> impl Iterator<Item = DrawView<'a>> {
+        self.draws.split(';').map(DrawView)
+    }
+
+    /// Return the smallest bag the game could have been played with, as `min_bag_for_game`
+    /// does.
+    pub fn min_bag(&self) -> Result<ColorCount, String> {
+        let mut res = ColorCount { r: 0, g: 0, b: 0 };
+        for d in self.draws() {
+            let d = d.counts()?;
+            res = ColorCount { r: res.r.max(d.r), g: res.g.max(d.g), b: res.b.max(d.b) };
+        }
+        Ok(res)
+    }
+
+    /// Return whether every draw fits in the bag.  Every draw is read, so a bad one is an
+    /// error even after the game is known to be impossible.
+    pub fn possible_with_bag(&self, bag: &ColorCount) -> Result<bool, String> {
+        let mut possible = true;
+        for d in self.draws() {
+            let d = d.counts()?;
+            possible &= d.r <= bag.r && d.g <= bag.g && d.b <= bag.b;
+        }
+        Ok(possible)
+    }
+
+    /// Read every draw into an owned `Game`.
+    pub fn to_game(&self) -> Result<Game, String> {
+        let draws = self.draws().map(|d| d.counts()).collect::<Result<_, _>>()?;
+        Ok(Game { id: self.id, draws })
+    }
+}
+
+impl<'a> DrawView<'a> {
+    /// Return whether the draw has no cubes written in it at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+
+    /// Return each count and color in the draw, in the order they are written.  An empty draw
+    /// has none.
+    pub fn cubes(&self) -> impl Iterator<Item = Result<(i32, &'a str), String>> {
+        let text = if self.is_empty() { None } else { Some(self.0) };
+        text.into_iter().flat_map(|t| t.split(',')).map(|s| {
+            let (countstr, colorstr) = s
+                .trim()
+                .split_once(' ')
+                .ok_or_else(|| format!("expected count and color, got `{}`", s.trim()))?;
+            let count = countstr
+                .parse::<i32>()
+                .map_err(|e| format!("bad count `{}`: {}", countstr, e))?;
+            Ok((count, colorstr))
+        })
+    }
+
+    /// Return the cubes of each color, adding together any color that is written twice.
+    pub fn counts(&self) -> Result<ColorCount, String> {
+        let mut d = ColorCount { r: 0, g: 0, b: 0 };
+        for cube in self.cubes() {
+            let (count, color) = cube?;
+            add(&mut d, count, color)?;
+        }
+        Ok(d)
+    }
+}
+
+/// Add `count` cubes of the color named `color` to `d`.
+pub(crate) fn add(d: &mut ColorCount, count: i32, color: &str) -> Result<(), String> {
+    *(match color {
+        "red" => &mut d.r,
+        "green" => &mut d.g,
+        "blue" => &mut d.b,
+        _ => return Err(format!("unknown color: {}", color)),
+    }) += count;
+    Ok(())
+}
+
+/// Return a view of every non-empty line of `input`.  Errors name the line they come from.
+pub fn game_views(input: &str) -> impl Iterator<Item = Result<GameView<'_>, String>> {
+    input
+        .split('\n')
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(n, line)| GameView::parse(line).map_err(|e| format!("line {}: {}", n + 1, e)))
+}
+
+/// Call `f` with a view of every non-empty line read from `reader`, threading `acc` through
+/// as `Iterator::try_fold` does.  Only one line is held in memory at a time.
+pub fn fold_games<R, T, F>(mut reader: R, mut acc: T, mut f: F) -> Result<T, String>
+where
+    R: BufRead,
+    F: FnMut(T, GameView) -> Result<T, String>,
+{
+    let mut line = String::new();
+    for n in 1.. {
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("line {}: {}", n, e))?;
+        if read == 0 {
+            break;
+        }
+        let text = line.strip_suffix('\n').unwrap_or(&line);
+        if text.is_empty() {
+            continue;
+        }
+        let at_line = |e| format!("line {}: {}", n, e);
+        acc = f(acc, GameView::parse(text).map_err(at_line)?).map_err(at_line)?;
+    }
+    Ok(acc)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "\
+Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+";
+
+#[test]
+fn test_game_view() {
+    let view = GameView::parse("Game 7: 3 blue, 4 red; ; 2 red, 1 red").unwrap();
+    assert_eq!(view.id, 7);
+    let draws: Vec<DrawView> = view.draws().collect();
+    assert_eq!(draws.len(), 3);
+    assert_eq!(draws[0].cubes().collect::<Vec<_>>(), vec![Ok((3, "blue")), Ok((4, "red"))]);
+    assert!(draws[1].is_empty());
+    assert_eq!(draws[1].cubes().count(), 0);
+    assert_eq!(draws[2].counts(), Ok(ColorCount { r: 3, g: 0, b: 0 }));
+    assert_eq!(view.min_bag(), Ok(ColorCount { r: 4, g: 0, b: 3 }));
+    assert_eq!(view.to_game(), crate::parseline("Game 7: 3 blue, 4 red; ; 2 red, 1 red"));
+}
+
+#[test]
+fn test_game_view_errors() {
+    assert!(GameView::parse("Game 1 3 blue").is_err());
+    assert!(GameView::parse("Game x: 3 blue").is_err());
+    let view = GameView::parse("Game 1: 30 red; 1 puce").unwrap();
+    assert_eq!(view.possible_with_bag(&crate::PART1_BAG), Err("unknown color: puce".to_string()));
+    assert!(view.min_bag().is_err());
+}
+
+#[test]
+fn test_game_views_match_parse_games() {
+    let views: Vec<Game> = game_views(EXAMPLE).map(|v| v.unwrap().to_game().unwrap()).collect();
+    assert_eq!(views, crate::parse_games(EXAMPLE).unwrap());
+    let err = game_views("Game 1: 1 red\n\nGame x: 1 red\n").find_map(Result::err);
+    assert_eq!(err.unwrap(), "line 3: bad game id in Game x: invalid digit found in string");
+}
+
+#[test]
+fn test_fold_games() {
+    let ids = fold_games(EXAMPLE.as_bytes(), vec![], |mut ids, v| {
+        ids.push(v.id);
+        Ok(ids)
+    });
+    assert_eq!(ids, Ok(vec![1, 2, 3]));
+    let power = |input: &str| {
+        fold_games(input.as_bytes(), 0, |total, v| {
+            let m = v.min_bag()?;
+            Ok(total + m.r * m.g * m.b)
+        })
+    };
+    assert_eq!(power(EXAMPLE), Ok(48 + 12 + 1560));
+    // No newline at the end, and a blank line in the middle.
+    assert_eq!(power("Game 1: 2 red, 3 green, 4 blue\n\nGame 2: 1 red, 1 green, 1 blue"), Ok(25));
+    assert_eq!(power("Game 1: 1 red\nGame 2: 1 puce\n"), Err("line 2: unknown color: puce".to_string()));
+}
@@ -0,0 +1,49 @@
+//! Compare scanning a schematic on one thread against scanning bands of rows in parallel.
+//!
+//! Run with `cargo bench`.  Grids are square and random: mostly blanks, with runs of digits and
+//! a scattering of symbols, so most numbers are part numbers and there are plenty of gears.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use day3::Options;
+
+#[path = "../src/testgen.rs"]
+mod testgen;
+
+/// Build an `n` by `n` schematic from a fixed seed.
+fn make_input(n: usize) -> String {
+    testgen::Rand::new(1).schematic(n, n, "0123456789............*#")
+}
+
+/// Run `f` repeatedly for about a second and return the mean time per call.
+fn time<F: FnMut() -> u64>(mut f: F) -> Duration {
+    let start = Instant::now();
+    let mut iters = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f());
+        iters += 1;
+    }
+    start.elapsed() / iters
+}
+
+fn main() {
+    let options = Options::default();
+    let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+    for n in [100, 1_000, 3_000] {
+        let input = make_input(n);
+        let serial = || day3::do_day3_checked::<u64>(black_box(&input), true, &options).unwrap();
+        let expected = serial();
+        println!("{:>5}x{:<5} {:>2} thread  {:>14?}", n, n, 1, time(serial));
+        let mut jobs_list = vec![2, 4, threads];
+        jobs_list.sort();
+        jobs_list.dedup();
+        for jobs in jobs_list.into_iter().filter(|&jobs| jobs > 1) {
+            let parallel = || {
+                day3::do_day3_parallel::<u64>(black_box(&input), true, &options, jobs).unwrap()
+            };
+            assert_eq!(parallel(), expected);
+            println!("{:>5}x{:<5} {:>2} threads {:>14?}", n, n, jobs, time(parallel));
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Reading signed and decimal numbers, such as `-42` and `3.5`, as single part values.
+//!
+//! A number is an optional sign, digits, and optionally a `.` followed by more digits.  `-` is
+//! a sign when a digit follows it and no digit comes before it, so `-42` and `*-4` hold
+//! negative numbers but `5-3` is 5 and 3 either side of a symbol.  A `.` with no digit on both
+//! sides is blank, as before.  The sign and the point are cells of the number, so the number
+//! touches what they touch.  Numbers do not carry on across a wrapped edge in this mode.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use grid::Grid;
+
+use crate::neighbourhood::step;
+use crate::schematic::read_grid;
+use crate::{is_symbol, Options};
+
+/// An exact decimal: `units` divided by 10 to the power of `scale`.  It is kept with no
+/// trailing zeros after the point, so equal values compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Decimal {
+    units: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    fn new(mut units: i128, mut scale: u32) -> Decimal {
+        while scale > 0 && units % 10 == 0 {
+            units /= 10;
+            scale -= 1;
+        }
+        Decimal { units, scale }
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let scale = self.scale.max(other.scale);
+        let a = self.units.checked_mul(10i128.checked_pow(scale - self.scale)?)?;
+        let b = other.units.checked_mul(10i128.checked_pow(scale - other.scale)?)?;
+        Some(Decimal::new(a.checked_add(b)?, scale))
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        Some(Decimal::new(self.units.checked_mul(other.units)?, self.scale + other.scale))
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Decimal, String> {
+        let bad = || format!("`{}` is not a decimal number", s);
+        let (negative, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (whole, fraction) = rest.split_once('.').unwrap_or((rest, ""));
+        let digits = |d: &str| d.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() || !digits(whole) || !digits(fraction) || rest.ends_with('.') {
+            return Err(bad());
+        }
+        let units: i128 = format!("{}{}", whole, fraction)
+            .parse()
+            .map_err(|_| format!("`{}` is too big", s))?;
+        let units = if negative { -units } else { units };
+        Ok(Decimal::new(units, fraction.len() as u32))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}", self.units);
+        }
+        let digits = format!("{:0>width$}", self.units.unsigned_abs(), width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        let sign = if self.units < 0 { "-" } else { "" };
+        write!(f, "{}{}.{}", sign, whole, fraction)
+    }
+}
+
+/// A number read from one row, and the columns of its cells, sign and point included.
+struct Token {
+    value: Decimal,
+    row: usize,
+    cols: Vec<usize>,
+}
+
+/// Read the numbers in a row, from the left.
+fn tokens(grid: &Grid<char>, row: usize) -> Result<Vec<Token>, String> {
+    let nc = grid.cols();
+    let digit = |c: usize| c < nc && grid[(row, c)].is_ascii_digit();
+    let mut res = vec![];
+    let mut c = 0;
+    while c < nc {
+        let sign = grid[(row, c)] == '-' && digit(c + 1) && !(c > 0 && digit(c - 1));
+        if !(sign || digit(c)) {
+            c += 1;
+            continue;
+        }
+        let start = c;
+        c += 1;
+        while digit(c) {
+            c += 1;
+        }
+        if c < nc && grid[(row, c)] == '.' && digit(c + 1) {
+            c += 1;
+            while digit(c) {
+                c += 1;
+            }
+        }
+        let text: String = (start..c).map(|c| grid[(row, c)]).collect();
+        let value = text
+            .parse()
+            .map_err(|_| format!("line {}, column {}: number is too big", row + 1, start + 1))?;
+        res.push(Token { value, row, cols: (start..c).collect() });
+    }
+    Ok(res)
+}
+
+/// Return the sum of part numbers, or of gear ratios for part 2, reading signed and decimal
+/// numbers, exactly.
+pub fn do_day3_decimal(input: &str, part2: bool, options: &Options) -> Result<Decimal, String> {
+    let grid = read_grid(input)?;
+    let (nr, nc) = grid.size();
+    let mut tokens_in = vec![];
+    for r in 0..nr {
+        tokens_in.extend(tokens(&grid, r)?);
+    }
+    // Which cells are taken by numbers, so that a `-` used as a sign is not also a symbol.
+    let mut taken = Grid::init(nr, nc, false);
+    for t in &tokens_in {
+        for &c in &t.cols {
+            taken[(t.row, c)] = true;
+        }
+    }
+
+    let offsets = options.neighbourhood.offsets();
+    let mut total = Decimal::default();
+    let mut stars: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for (i, t) in tokens_in.iter().enumerate() {
+        let mut symbols: Vec<(usize, usize)> = t
+            .cols
+            .iter()
+            .flat_map(|&c| {
+                offsets.iter().filter_map(move |&o| step((t.row, c), o, nr, nc, options.wrap))
+            })
+            .filter(|&pos| is_symbol(grid[pos]) && !taken[pos])
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        if !symbols.is_empty() && !part2 {
+            let (r, c) = (t.row, t.cols[0]);
+            total = total
+                .checked_add(t.value)
+                .ok_or_else(|| format!("line {}, column {}: sum is too big", r + 1, c + 1))?;
+        }
+        for pos in symbols.into_iter().filter(|&pos| grid[pos] == '*') {
+            stars.entry(pos).or_default().push(i);
+        }
+    }
+    if part2 {
+        for ((r, c), ts) in stars {
+            if let [a, b] = ts[..] {
+                let too_big = || format!("line {}, column {}: gear ratio is too big", r + 1, c + 1);
+                let ratio =
+                    tokens_in[a].value.checked_mul(tokens_in[b].value).ok_or_else(too_big)?;
+                total = total.checked_add(ratio).ok_or_else(too_big)?;
+            }
+        }
+    }
+    Ok(total)
+}
+
+#[test]
+fn test_decimal() {
+    let d = |s: &str| s.parse::<Decimal>().unwrap();
+    assert_eq!(d("3.50"), d("3.5"));
+    assert_eq!(d("-0"), d("0"));
+    assert_eq!(d("3.5").checked_add(d("-1.25")).unwrap().to_string(), "2.25");
+    assert_eq!(d("0.1").checked_add(d("0.2")).unwrap(), d("0.3"));
+    assert_eq!(d("-2.5").checked_mul(d("0.4")).unwrap().to_string(), "-1");
+    assert_eq!(d("-0.05").to_string(), "-0.05");
+    assert_eq!(d("12").to_string(), "12");
+    assert!("3.".parse::<Decimal>().is_err());
+    assert!(".5".parse::<Decimal>().is_err());
+    assert!("1-2".parse::<Decimal>().is_err());
+}
+
+#[test]
+fn test_do_day3_decimal() {
+    let options = Options::default();
+    let sum = |input: &str, part2| do_day3_decimal(input, part2, &options).unwrap().to_string();
+    // The old reading sees a symbol and 42, and 3 and 5 either side of a blank, with 5 out of
+    // reach of the `#`.
+    assert_eq!(crate::do_day3("-42#3.5", false), 42 + 3);
+    assert_eq!(sum("-42#3.5", false), "-38.5");
+    // A `-` after a digit is a symbol; after anything else, before a digit, it is a sign.
+    assert_eq!(sum("5-3.....", false), "8");
+    assert_eq!(sum("#-4.....", false), "-4");
+    assert_eq!(sum("-......\n.....-1", false), "0");
+    // Points with no digit on one side are blank, so 3 and 5 do not reach the `#` through
+    // them, but a point inside a number does.
+    assert_eq!(sum("3.#.5", false), "0");
+    assert_eq!(sum("3.5#", false), "3.5");
+    // A gear of -2.5 and 4.  `.5` is not a number, so the second star is only a gear once it
+    // is `0.5`.
+    assert_eq!(sum("-2.5*4\n......\n0.5*.5", true), "-10");
+    assert_eq!(sum("-2.5*4.\n.......\n0.5*0.5", true), "-9.75");
+}
+
+#[test]
+fn test_do_day3_decimal_errors() {
+    let options = Options::default();
+    assert_eq!(
+        do_day3_decimal("1.x", false, &options).unwrap_err(),
+        "line 1: unexpected character `x`"
+    );
+    let big = format!("..{}#", "9".repeat(40));
+    assert_eq!(
+        do_day3_decimal(&big, false, &options).unwrap_err(),
+        "line 1, column 3: number is too big"
+    );
+}
@@ -0,0 +1,284 @@
+//! What changed between two versions of a schematic, and how that moved the answers.
+//!
+//! Numbers are matched by the cell of their first digit, and gears by the cell of their `*`.
+//! Each change to a part number falls in exactly one of added, removed, changed, gained and
+//! lost, so those lists add up to the change in part 1; likewise the gear lists add up to the
+//! change in part 2.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::{Number, Schematic};
+
+/// A cell whose character changed.  Cells off the edge of one of the schematics count as `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub row: usize,
+    pub col: usize,
+    pub old: char,
+    pub new: char,
+}
+
+/// A `*` touching exactly two numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gear {
+    pub row: usize,
+    pub col: usize,
+    pub ratio: i64,
+}
+
+/// The differences between an old and a new schematic.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SchematicDiff {
+    pub cells: Vec<CellChange>,
+    /// Part numbers starting where there was no part number.
+    pub added: Vec<Number>,
+    /// Part numbers with no part number starting where they did.
+    pub removed: Vec<Number>,
+    /// Part numbers whose value changed, old and new.
+    pub changed: Vec<(Number, Number)>,
+    /// Numbers that are the same but now touch a symbol.
+    pub gained: Vec<Number>,
+    /// Numbers that are the same but no longer touch a symbol.
+    pub lost: Vec<Number>,
+    pub gears_appeared: Vec<Gear>,
+    pub gears_disappeared: Vec<Gear>,
+    /// Gears at the same place whose ratio changed, old and new.
+    pub gears_changed: Vec<(Gear, Gear)>,
+    pub old_part1: i64,
+    pub new_part1: i64,
+    pub old_part2: i64,
+    pub new_part2: i64,
+}
+
+/// Each number by the cell of its first digit, with whether it is a part number.
+fn numbers_by_start(s: &Schematic) -> BTreeMap<(usize, usize), (&Number, bool)> {
+    s.numbers
+        .iter()
+        .zip(&s.number_symbols)
+        .map(|(n, syms)| (n.start(), (n, !syms.is_empty())))
+        .collect()
+}
+
+fn gears_by_cell(s: &Schematic) -> Result<BTreeMap<(usize, usize), Gear>, String> {
+    Ok(s.gears()?
+        .into_iter()
+        .map(|(i, ratio)| {
+            let sym = &s.symbols[i];
+            ((sym.row, sym.col), Gear { row: sym.row, col: sym.col, ratio })
+        })
+        .collect())
+}
+
+/// Compare two schematics.  Returns an error if either answer is too big for an `i64`.
+pub fn diff(old: &Schematic, new: &Schematic) -> Result<SchematicDiff, String> {
+    let mut d = SchematicDiff {
+        old_part1: old.part1()?,
+        new_part1: new.part1()?,
+        old_part2: old.part2()?,
+        new_part2: new.part2()?,
+        ..SchematicDiff::default()
+    };
+
+    let (or, oc) = old.grid.size();
+    let (nr, nc) = new.grid.size();
+    for row in 0..or.max(nr) {
+        for col in 0..oc.max(nc) {
+            let old = old.grid.get(row, col).copied().unwrap_or('.');
+            let new = new.grid.get(row, col).copied().unwrap_or('.');
+            if old != new {
+                d.cells.push(CellChange { row, col, old, new });
+            }
+        }
+    }
+
+    let old_numbers = numbers_by_start(old);
+    let new_numbers = numbers_by_start(new);
+    let starts: BTreeSet<_> = old_numbers.keys().chain(new_numbers.keys()).collect();
+    for start in starts {
+        match (old_numbers.get(start), new_numbers.get(start)) {
+            (Some(&(a, a_part)), Some(&(b, b_part))) if a == b => {
+                if b_part && !a_part {
+                    d.gained.push(b.clone());
+                } else if a_part && !b_part {
+                    d.lost.push(a.clone());
+                }
+            }
+            (Some(&(a, true)), Some(&(b, true))) => d.changed.push((a.clone(), b.clone())),
+            (a, b) => {
+                if let Some(&(a, true)) = a {
+                    d.removed.push(a.clone());
+                }
+                if let Some(&(b, true)) = b {
+                    d.added.push(b.clone());
+                }
+            }
+        }
+    }
+
+    let old_gears = gears_by_cell(old)?;
+    let new_gears = gears_by_cell(new)?;
+    for (cell, &a) in &old_gears {
+        match new_gears.get(cell) {
+            None => d.gears_disappeared.push(a),
+            Some(&b) if b != a => d.gears_changed.push((a, b)),
+            Some(_) => {}
+        }
+    }
+    for (cell, &b) in &new_gears {
+        if !old_gears.contains_key(cell) {
+            d.gears_appeared.push(b);
+        }
+    }
+    Ok(d)
+}
+
+// The answers are sums of values that are never negative, so the deltas always fit in an `i64`.
+impl SchematicDiff {
+    pub fn part1_delta(&self) -> i64 {
+        self.new_part1 - self.old_part1
+    }
+
+    pub fn part2_delta(&self) -> i64 {
+        self.new_part2 - self.old_part2
+    }
+}
+
+/// Rows and columns are counted from 1, as `day3 at` takes them and errors report them.
+impl fmt::Display for SchematicDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let at = |n: &Number| format!("{} at ({}, {})", n.value, n.row + 1, n.cols[0] + 1);
+        for c in &self.cells {
+            writeln!(f, "cell ({}, {}): {} -> {}", c.row + 1, c.col + 1, c.old, c.new)?;
+        }
+        for n in &self.added {
+            writeln!(f, "added {}", at(n))?;
+        }
+        for n in &self.removed {
+            writeln!(f, "removed {}", at(n))?;
+        }
+        for (a, b) in &self.changed {
+            writeln!(f, "changed {} -> {}", at(a), at(b))?;
+        }
+        for n in &self.gained {
+            writeln!(f, "gained a symbol: {}", at(n))?;
+        }
+        for n in &self.lost {
+            writeln!(f, "lost its symbols: {}", at(n))?;
+        }
+        for g in &self.gears_appeared {
+            writeln!(f, "gear appeared at ({}, {}): ratio {}", g.row + 1, g.col + 1, g.ratio)?;
+        }
+        for g in &self.gears_disappeared {
+            let (row, col) = (g.row + 1, g.col + 1);
+            writeln!(f, "gear disappeared at ({}, {}): ratio {}", row, col, g.ratio)?;
+        }
+        for (a, b) in &self.gears_changed {
+            let (row, col) = (a.row + 1, a.col + 1);
+            writeln!(f, "gear changed at ({}, {}): ratio {} -> {}", row, col, a.ratio, b.ratio)?;
+        }
+        writeln!(f, "part1 {} -> {} ({:+})", self.old_part1, self.new_part1, self.part1_delta())?;
+        writeln!(f, "part2 {} -> {} ({:+})", self.old_part2, self.new_part2, self.part2_delta())
+    }
+}
+
+#[cfg(test)]
+fn check_deltas(d: &SchematicDiff) {
+    let sum = |ns: &[Number]| ns.iter().map(|n| n.value).sum::<i64>();
+    let changed: i64 = d.changed.iter().map(|(a, b)| b.value - a.value).sum();
+    let part1 = sum(&d.added) - sum(&d.removed) + changed + sum(&d.gained) - sum(&d.lost);
+    assert_eq!(part1, d.part1_delta());
+    let ratios = |gs: &[Gear]| gs.iter().map(|g| g.ratio).sum::<i64>();
+    let changed: i64 = d.gears_changed.iter().map(|(a, b)| b.ratio - a.ratio).sum();
+    let part2 = ratios(&d.gears_appeared) - ratios(&d.gears_disappeared) + changed;
+    assert_eq!(part2, d.part2_delta());
+}
+
+#[test]
+fn test_diff() {
+    let options = crate::Options::default();
+    let old = Schematic::parse(crate::schematic::EXAMPLE, &options).unwrap();
+    // 114 gains a `#`, 35 becomes 356, 755 is cut down to 7 and a 5 that touches nothing,
+    // and 664 loses its `$`.
+    let new = "\
+467..114..
+...*..#...
+..356.633.
+......#...
+617*......
+.....+.58.
+..592.....
+......7.5.
+.....*....
+.664.598..";
+    let new = Schematic::parse(new, &options).unwrap();
+    let d = diff(&old, &new).unwrap();
+    assert_eq!(d.cells.len(), 4);
+    assert_eq!(d.cells[0], CellChange { row: 1, col: 6, old: '.', new: '#' });
+    let values = |ns: &[Number]| ns.iter().map(|n| n.value).collect::<Vec<_>>();
+    assert!(d.added.is_empty() && d.removed.is_empty());
+    let changed: Vec<_> = d.changed.iter().map(|(a, b)| (a.value, b.value)).collect();
+    assert_eq!(changed, vec![(35, 356), (755, 7)]);
+    assert_eq!(values(&d.gained), vec![114]);
+    assert_eq!(values(&d.lost), vec![664]);
+    assert!(d.gears_appeared.is_empty() && d.gears_disappeared.is_empty());
+    assert_eq!(d.gears_changed.len(), 2);
+    assert_eq!(d.gears_changed[1].1, Gear { row: 8, col: 5, ratio: 7 * 598 });
+    check_deltas(&d);
+    // And the other way round.
+    let d = diff(&new, &old).unwrap();
+    assert_eq!((values(&d.gained), values(&d.lost)), (vec![664], vec![114]));
+    check_deltas(&d);
+    // Nothing changes against itself.
+    let d = diff(&old, &old).unwrap();
+    assert!(d.cells.is_empty() && d.changed.is_empty() && d.gears_changed.is_empty());
+    assert_eq!((d.part1_delta(), d.part2_delta()), (0, 0));
+}
+
+#[test]
+fn test_diff_gears_and_sizes() {
+    let options = crate::Options::default();
+    let old = Schematic::parse("1*2\n...", &options).unwrap();
+    let new = Schematic::parse("1*.\n...\n.3.", &options).unwrap();
+    let d = diff(&old, &new).unwrap();
+    assert_eq!(
+        d.cells,
+        vec![
+            CellChange { row: 0, col: 2, old: '2', new: '.' },
+            CellChange { row: 2, col: 1, old: '.', new: '3' },
+        ]
+    );
+    assert_eq!(d.gears_disappeared, vec![Gear { row: 0, col: 1, ratio: 2 }]);
+    // The text counts rows and columns from 1.
+    assert_eq!(
+        d.to_string(),
+        "\
+cell (1, 3): 2 -> .
+cell (3, 2): . -> 3
+removed 2 at (1, 3)
+gear disappeared at (1, 2): ratio 2
+part1 3 -> 1 (-2)
+part2 2 -> 0 (-2)
+"
+    );
+    check_deltas(&d);
+    let d = diff(&new, &old).unwrap();
+    assert_eq!(d.gears_appeared, vec![Gear { row: 0, col: 1, ratio: 2 }]);
+    assert!(d.to_string().contains("added 2 at (1, 3)\ngear appeared at (1, 2): ratio 2\n"));
+    check_deltas(&d);
+}
+
+#[test]
+fn test_diff_overflow() {
+    let options = crate::Options::default();
+    // 11 digits, too many for an i32, are fine.
+    let old = Schematic::parse("99999999999#", &options).unwrap();
+    let new = Schematic::parse("99999999998#", &options).unwrap();
+    assert_eq!(diff(&old, &new).unwrap().part1_delta(), -1);
+    // The new gear's ratio is too big for an i64.
+    let old = Schematic::parse("3037000500.3037000500", &options).unwrap();
+    let new = Schematic::parse("3037000500*3037000500", &options).unwrap();
+    let e = "line 1, column 11: gear ratio is too big for i64".to_string();
+    assert_eq!(diff(&old, &new), Err(e.clone()));
+    assert_eq!(diff(&new, &old), Err(e));
+}
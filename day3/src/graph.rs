@@ -0,0 +1,237 @@
+//! Which parts are connected: numbers that share a symbol, symbols that share a number, and
+//! so on.
+//!
+//! The graph has a node for every number and every symbol, and an edge wherever a number
+//! touches a symbol.  Each connected component is one assembly.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{too_big, Schematic};
+
+/// A set of numbers and symbols that are all connected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Component {
+    /// Indexes into `Schematic::numbers`, in order.
+    pub numbers: Vec<usize>,
+    /// Indexes into `Schematic::symbols`, in order.
+    pub symbols: Vec<usize>,
+    /// The sum of the numbers' values.
+    pub sum: i64,
+    /// How many of each kind of symbol there are.
+    pub symbol_kinds: BTreeMap<char, usize>,
+}
+
+#[derive(Clone, Copy)]
+enum Node {
+    Number(usize),
+    Symbol(usize),
+}
+
+/// Return every connected component, including lone numbers and lone symbols.  Components
+/// are in the order of their first number, and then of their first symbol for those with no
+/// numbers.  Returns an error naming the first number of a component whose sum is too big for
+/// an `i64`.
+pub fn components(s: &Schematic) -> Result<Vec<Component>, String> {
+    let mut number_seen = vec![false; s.numbers.len()];
+    let mut symbol_seen = vec![false; s.symbols.len()];
+    let roots = (0..s.numbers.len())
+        .map(Node::Number)
+        .chain((0..s.symbols.len()).map(Node::Symbol));
+    let mut res = vec![];
+    for root in roots {
+        let mut c = Component {
+            numbers: vec![],
+            symbols: vec![],
+            sum: 0,
+            symbol_kinds: BTreeMap::new(),
+        };
+        let mut todo = vec![root];
+        while let Some(node) = todo.pop() {
+            match node {
+                Node::Number(i) if !number_seen[i] => {
+                    number_seen[i] = true;
+                    c.numbers.push(i);
+                    todo.extend(s.number_symbols[i].iter().map(|&j| Node::Symbol(j)));
+                }
+                Node::Symbol(i) if !symbol_seen[i] => {
+                    symbol_seen[i] = true;
+                    c.symbols.push(i);
+                    todo.extend(s.symbol_numbers[i].iter().map(|&j| Node::Number(j)));
+                }
+                _ => {}
+            }
+        }
+        if c.numbers.is_empty() && c.symbols.is_empty() {
+            continue;
+        }
+        c.numbers.sort();
+        c.symbols.sort();
+        let sum = c.numbers.iter().try_fold(0i64, |sum, &i| sum.checked_add(s.numbers[i].value));
+        c.sum = sum.ok_or_else(|| {
+            let (r, col) = s.numbers[c.numbers[0]].start();
+            too_big::<i64>(format!("line {}, column {}: sum of the assembly", r + 1, col + 1))
+        })?;
+        for &i in &c.symbols {
+            *c.symbol_kinds.entry(s.symbols[i].ch).or_insert(0) += 1;
+        }
+        res.push(c);
+    }
+    Ok(res)
+}
+
+/// Return the graph in Graphviz DOT format.  Numbers are ellipses named `nI` and symbols are
+/// boxes named `sI`, after their indexes; each component of more than one node is a cluster.
+pub fn dot_graph(s: &Schematic) -> Result<String, String> {
+    let mut out = String::from("graph schematic {\n");
+    for (i, n) in s.numbers.iter().enumerate() {
+        out.push_str(&format!("  n{} [label=\"{}\"];\n", i, n.value));
+    }
+    for (i, sym) in s.symbols.iter().enumerate() {
+        out.push_str(&format!("  s{} [label=\"{}\", shape=box];\n", i, sym.ch));
+    }
+    for (k, c) in components(s)?.iter().enumerate() {
+        if c.numbers.len() + c.symbols.len() < 2 {
+            continue;
+        }
+        out.push_str(&format!("  subgraph cluster_{} {{\n    label=\"sum {}\";\n", k, c.sum));
+        for &i in &c.numbers {
+            out.push_str(&format!("    n{};\n", i));
+        }
+        for &i in &c.symbols {
+            out.push_str(&format!("    s{};\n", i));
+        }
+        out.push_str("  }\n");
+    }
+    for (i, syms) in s.number_symbols.iter().enumerate() {
+        for j in syms {
+            out.push_str(&format!("  n{} -- s{};\n", i, j));
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum JsonNode {
+    Number { id: String, value: i64, row: usize, col: usize },
+    Symbol { id: String, symbol: char, row: usize, col: usize },
+}
+
+#[derive(Serialize)]
+struct JsonGraph {
+    nodes: Vec<JsonNode>,
+    edges: Vec<(String, String)>,
+    components: Vec<Component>,
+}
+
+/// Return the graph as JSON: `nodes` with the same ids as `dot_graph`, `edges` as pairs of ids,
+/// and `components`.
+pub fn json_graph(s: &Schematic) -> Result<String, String> {
+    let numbers = s.numbers.iter().enumerate().map(|(i, n)| {
+        let (row, col) = n.start();
+        JsonNode::Number { id: format!("n{}", i), value: n.value, row, col }
+    });
+    let symbols = s.symbols.iter().enumerate().map(|(i, sym)| JsonNode::Symbol {
+        id: format!("s{}", i),
+        symbol: sym.ch,
+        row: sym.row,
+        col: sym.col,
+    });
+    let edges = s
+        .number_symbols
+        .iter()
+        .enumerate()
+        .flat_map(|(i, syms)| {
+            syms.iter().map(move |j| (format!("n{}", i), format!("s{}", j)))
+        })
+        .collect();
+    let graph = JsonGraph {
+        nodes: numbers.chain(symbols).collect(),
+        edges,
+        components: components(s)?,
+    };
+    Ok(serde_json::to_string_pretty(&graph).expect("graph should always serialize"))
+}
+
+#[test]
+fn test_components() {
+    let s = Schematic::parse(crate::schematic::EXAMPLE, &crate::Options::default()).unwrap();
+    let cs = components(&s).unwrap();
+    // 467 and 35 share a star; 617 is on its own star; 114 and 58 are loose.
+    assert_eq!(cs[0].numbers, vec![0, 2]);
+    assert_eq!(cs[0].sum, 467 + 35);
+    assert_eq!(cs[0].symbol_kinds, BTreeMap::from([('*', 1)]));
+    assert_eq!(cs[1].numbers, vec![1]);
+    assert!(cs[1].symbols.is_empty());
+    assert_eq!(cs.len(), 8);
+    assert_eq!(cs.iter().map(|c| c.sum).sum::<i64>(), 4361 + 114 + 58);
+    // Every node is in exactly one component.
+    assert_eq!(cs.iter().map(|c| c.numbers.len()).sum::<usize>(), s.numbers.len());
+    assert_eq!(cs.iter().map(|c| c.symbols.len()).sum::<usize>(), s.symbols.len());
+}
+
+#[test]
+fn test_components_transitive() {
+    // 1 and 2 share the `#`, 2 and 3 share the `*`, and the `$` touches nothing.
+    let s = Schematic::parse("1#2*3\n.....\n$....", &crate::Options::default()).unwrap();
+    let cs = components(&s).unwrap();
+    assert_eq!(cs.len(), 2);
+    assert_eq!(cs[0].numbers, vec![0, 1, 2]);
+    assert_eq!(cs[0].symbols, vec![0, 1]);
+    assert_eq!(cs[0].sum, 6);
+    assert_eq!(cs[0].symbol_kinds, BTreeMap::from([('#', 1), ('*', 1)]));
+    assert_eq!((cs[1].numbers.len(), cs[1].symbols.clone()), (0, vec![2]));
+}
+
+#[test]
+fn test_dot_graph() {
+    let s = Schematic::parse("1#2\n...", &crate::Options::default()).unwrap();
+    assert_eq!(
+        dot_graph(&s).unwrap(),
+        "\
+graph schematic {
+  n0 [label=\"1\"];
+  n1 [label=\"2\"];
+  s0 [label=\"#\", shape=box];
+  subgraph cluster_0 {
+    label=\"sum 3\";
+    n0;
+    n1;
+    s0;
+  }
+  n0 -- s0;
+  n1 -- s0;
+}
+"
+    );
+}
+
+#[test]
+fn test_json_graph() {
+    let s = Schematic::parse("1#2\n..9", &crate::Options::default()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json_graph(&s).unwrap()).unwrap();
+    assert_eq!(json["nodes"][0]["kind"], "number");
+    assert_eq!(json["nodes"][3]["symbol"], "#");
+    assert_eq!(json["edges"].as_array().unwrap().len(), 3);
+    assert_eq!(json["edges"][2], serde_json::json!(["n2", "s0"]));
+    assert_eq!(json["components"][0]["sum"], 12);
+    assert_eq!(json["components"][0]["symbol_kinds"]["#"], 1);
+}
+
+#[test]
+fn test_components_overflow() {
+    let options = crate::Options::default();
+    // 11 digits, too many for an i32, are fine.
+    let s = Schematic::parse("99999999999#", &options).unwrap();
+    assert_eq!(components(&s).unwrap()[0].sum, 99999999999);
+    // Each number fits, but not their sum.
+    let big = "9000000000000000000#9000000000000000000";
+    let s = Schematic::parse(&format!("{}\n{}", ".".repeat(big.len()), big), &options).unwrap();
+    let e = "line 2, column 1: sum of the assembly is too big for i64".to_string();
+    assert_eq!(components(&s), Err(e.clone()));
+    assert_eq!(dot_graph(&s), Err(e.clone()));
+    assert_eq!(json_graph(&s), Err(e));
+}
@@ -0,0 +1,223 @@
+//! Schematics of stacked boards: several layers of the same size, separated by blank lines.
+//!
+//! Numbers still run along a row of one layer.  A digit touches the cells of its own layer as
+//! the neighbourhood says, and in the layers just above and below, the cell straight across
+//! and the cells at the same offsets.  With the default neighbourhood that is all 26 cells of
+//! the 3x3x3 cube around it.  Layers do not wrap round, though each layer's edges can.
+
+use crate::neighbourhood::step;
+use crate::{too_big, Cell, Options, Schematic};
+
+/// A number or symbol: the index of its layer, and its index within that layer.
+pub type LayerIndex = (usize, usize);
+
+/// A stack of schematics, and which numbers touch which symbols across all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayeredSchematic {
+    pub layers: Vec<Schematic>,
+    /// For each layer and each number in it, the symbols it touches in any layer, in order.
+    pub number_symbols: Vec<Vec<Vec<LayerIndex>>>,
+    /// For each layer and each symbol in it, the numbers it touches in any layer, in order.
+    pub symbol_numbers: Vec<Vec<Vec<LayerIndex>>>,
+}
+
+/// Split `input` into layers at blank lines.
+pub fn split_layers(input: &str) -> Vec<&str> {
+    input
+        .split("\n\n")
+        .map(|layer| layer.trim_matches('\n'))
+        .filter(|layer| !layer.is_empty())
+        .collect()
+}
+
+impl LayeredSchematic {
+    /// Read every layer and work out which numbers touch which symbols.  All layers must be
+    /// the same size.
+    pub fn parse(input: &str, options: &Options) -> Result<LayeredSchematic, String> {
+        let layers = split_layers(input)
+            .iter()
+            .enumerate()
+            .map(|(z, layer)| {
+                Schematic::parse(layer, options).map_err(|e| format!("layer {}: {}", z + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(z) = layers.iter().position(|l| l.grid.size() != layers[0].grid.size()) {
+            let (nr, nc) = layers[z].grid.size();
+            let (er, ec) = layers[0].grid.size();
+            return Err(format!("layer {}: is {}x{}, expected {}x{}", z + 1, nr, nc, er, ec));
+        }
+        Ok(LayeredSchematic::from_layers(layers))
+    }
+
+    pub fn from_layers(layers: Vec<Schematic>) -> LayeredSchematic {
+        // Within a layer, the adjacency is already known.
+        let mut number_symbols: Vec<Vec<Vec<LayerIndex>>> = layers
+            .iter()
+            .enumerate()
+            .map(|(z, l)| {
+                l.number_symbols.iter().map(|ss| ss.iter().map(|&s| (z, s)).collect()).collect()
+            })
+            .collect();
+        let mut symbol_numbers: Vec<Vec<Vec<LayerIndex>>> = layers
+            .iter()
+            .enumerate()
+            .map(|(z, l)| {
+                l.symbol_numbers.iter().map(|ns| ns.iter().map(|&n| (z, n)).collect()).collect()
+            })
+            .collect();
+
+        // Across layers, from each digit to the cells above and below it.
+        for (z, layer) in layers.iter().enumerate() {
+            let (nr, nc) = layer.grid.size();
+            let mut offsets = layer.options.neighbourhood.offsets();
+            offsets.push((0, 0));
+            let others = [z.checked_sub(1), Some(z + 1).filter(|&z| z < layers.len())];
+            for (i, n) in layer.numbers.iter().enumerate() {
+                let mut touching = vec![];
+                for &c in &n.cols {
+                    for &o in &offsets {
+                        let Some((r, c)) = step((n.row, c), o, nr, nc, layer.options.wrap) else {
+                            continue;
+                        };
+                        for oz in others.iter().flatten() {
+                            if let Some(Cell::Symbol(s)) = layers[*oz].cell(r, c) {
+                                touching.push((*oz, s));
+                            }
+                        }
+                    }
+                }
+                touching.sort();
+                touching.dedup();
+                for &(oz, s) in &touching {
+                    symbol_numbers[oz][s].push((z, i));
+                }
+                number_symbols[z][i].extend(touching);
+                number_symbols[z][i].sort();
+            }
+        }
+        for ns in symbol_numbers.iter_mut().flatten() {
+            ns.sort();
+        }
+        LayeredSchematic { layers, number_symbols, symbol_numbers }
+    }
+
+    /// The sum of the numbers that touch a symbol in any layer, or an error if it is too big
+    /// for an `i64`.
+    pub fn part1(&self) -> Result<i64, String> {
+        let mut total: i64 = 0;
+        for (layer, touching) in self.layers.iter().zip(&self.number_symbols) {
+            for (n, ss) in layer.numbers.iter().zip(touching) {
+                if !ss.is_empty() {
+                    total = total
+                        .checked_add(n.value)
+                        .ok_or_else(|| too_big::<i64>("sum of part numbers".into()))?;
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// The sum of the ratios of the `*`s that touch exactly two numbers in any layers, or an
+    /// error naming the first gear whose ratio, or the sum, is too big for an `i64`.
+    pub fn part2(&self) -> Result<i64, String> {
+        let mut total: i64 = 0;
+        for (z, (layer, touching)) in self.layers.iter().zip(&self.symbol_numbers).enumerate() {
+            for (s, ns) in layer.symbols.iter().zip(touching) {
+                if let [(za, a), (zb, b)] = ns[..] {
+                    if s.ch == '*' {
+                        let value = |z: usize, i: usize| self.layers[z].numbers[i].value;
+                        let gear = format!(
+                            "layer {}: line {}, column {}: gear ratio",
+                            z + 1,
+                            s.row + 1,
+                            s.col + 1
+                        );
+                        let ratio = value(za, a)
+                            .checked_mul(value(zb, b))
+                            .ok_or_else(|| too_big::<i64>(gear))?;
+                        total = total
+                            .checked_add(ratio)
+                            .ok_or_else(|| too_big::<i64>("sum of gear ratios".into()))?;
+                    }
+                }
+            }
+        }
+        Ok(total)
+    }
+}
+
+#[test]
+fn test_split_layers() {
+    assert_eq!(split_layers("1.\n.*\n\n..\n2.\n"), vec!["1.\n.*", "..\n2."]);
+    assert_eq!(split_layers("1.\n.*\n\n\n\n..\n2."), vec!["1.\n.*", "..\n2."]);
+    assert_eq!(split_layers("1.\n.*\n"), vec!["1.\n.*"]);
+}
+
+#[test]
+fn test_single_layer_is_unchanged() {
+    let s = LayeredSchematic::parse(crate::schematic::EXAMPLE, &Options::default()).unwrap();
+    assert_eq!(s.layers.len(), 1);
+    assert_eq!(s.part1(), Ok(4361));
+    assert_eq!(s.part2(), Ok(467835));
+}
+
+#[test]
+fn test_layers() {
+    // 12 is under the `#`; 3 is diagonally under the `*` and 4 beside it, making a gear;
+    // 5 is straight over the `$`, but two layers away.
+    let input = "\
+12...
+.....
+$.3..
+
+#....
+...*4
+.....
+
+.....
+.....
+5....
+";
+    let s = LayeredSchematic::parse(input, &Options::default()).unwrap();
+    assert_eq!(s.layers.len(), 3);
+    assert_eq!(s.number_symbols[0][0], vec![(1, 0)]);
+    assert_eq!(s.symbol_numbers[1][1], vec![(0, 1), (1, 0)]);
+    assert!(s.symbol_numbers[0][0].is_empty());
+    assert!(s.number_symbols[2][0].is_empty());
+    assert_eq!(s.part1(), Ok(12 + 3 + 4));
+    assert_eq!(s.part2(), Ok(3 * 4));
+    // Each layer alone has only 4 touching the `*`.
+    assert_eq!(s.layers[1].part1(), Ok(4));
+    let von_neumann =
+        Options { neighbourhood: crate::Neighbourhood::VonNeumann, ..Options::default() };
+    let s = LayeredSchematic::parse(input, &von_neumann).unwrap();
+    assert_eq!(s.part1(), Ok(12 + 4));
+    assert_eq!(s.part2(), Ok(0));
+}
+
+#[test]
+fn test_layer_errors() {
+    let options = Options::default();
+    assert_eq!(
+        LayeredSchematic::parse("1.\n.*\n\n1.\n.*.", &options).unwrap_err(),
+        "layer 2: line 2: has 3 cells, expected 2"
+    );
+    assert_eq!(
+        LayeredSchematic::parse("1.\n.*\n\n1..\n.*.", &options).unwrap_err(),
+        "layer 2: is 2x3, expected 2x2"
+    );
+}
+
+#[test]
+fn test_layer_overflow() {
+    let options = Options::default();
+    // Each number fits in its layer, but the `*` between them makes a ratio too big.
+    let s = LayeredSchematic::parse("3037000500\n\n*.........\n\n3037000500", &options);
+    assert_eq!(
+        s.unwrap().part2(),
+        Err("layer 2: line 1, column 1: gear ratio is too big for i64".to_string())
+    );
+    let big = "9000000000000000000#";
+    let s = LayeredSchematic::parse(&format!("{}\n\n{}", big, big), &options).unwrap();
+    assert_eq!(s.part1(), Err("sum of part numbers is too big for i64".to_string()));
+}
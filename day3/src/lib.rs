@@ -1,28 +1,108 @@
 use grid::Grid;
-use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+
+mod decimal;
+mod diff;
+mod graph;
+mod layers;
+mod live;
+mod neighbourhood;
+mod parallel;
+mod schematic;
+#[cfg(test)]
+mod testgen;
+
+pub use decimal::{do_day3_decimal, Decimal};
+pub use diff::{diff, CellChange, Gear, SchematicDiff};
+pub use graph::{components, dot_graph, json_graph, Component};
+pub use layers::{split_layers, LayerIndex, LayeredSchematic};
+pub use live::LiveSchematic;
+use neighbourhood::step;
+pub use neighbourhood::{Neighbourhood, Wrap};
+pub use parallel::do_day3_parallel;
+use schematic::reading_order;
+pub use schematic::{read_grid, Cell, Number, Schematic, Symbol};
 
 /// Return the sum of calibration values.
 pub fn do_day3_part1(input: &str) -> i32 {
     do_day3(input, false)
 }
 
-    /// Return the sum of calibration values.
+/// Return the sum of calibration values.
 pub fn do_day3(input: &str, part2: bool) -> i32 {
+    do_day3_with(input, part2, &Options::default())
+}
+
+/// How to read a schematic.  The default is the puzzle's: 8 neighbours on a flat sheet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Options {
+    pub neighbourhood: Neighbourhood,
+    pub wrap: Wrap,
+}
+
+/// A type to add up the answers in.  Overflowing it is an error rather than wrapping round, so
+/// a wider type such as `u64` or `u128` gives room for longer numbers and bigger gears.
+pub trait Answer: Copy + From<u8> + fmt::Debug + fmt::Display {
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_answer {
+    ($($t:ty)*) => {
+        $(impl Answer for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+        })*
+    };
+}
+
+impl_answer!(i32 i64 u32 u64 u128);
+
+/// Whether `c` is a symbol that makes a number next to it a part number.
+fn is_symbol(c: char) -> bool {
+    matches!(c, '@' | '#' | '$' | '%' | '&' | '*' | '-' | '+' | '=' | '/')
+}
+
+/// Return the sum of part numbers, or of gear ratios for part 2, where a number touches the
+/// symbols in the neighbourhood of any of its digits.  When columns wrap, a number running off
+/// the end of a row carries on at the start of it.
+///
+/// Panics if a number, a gear ratio or the answer is too big for an `i32`; see
+/// `do_day3_checked` to choose a wider type or to get an error instead.
+pub fn do_day3_with(input: &str, part2: bool, options: &Options) -> i32 {
+    do_day3_checked(input, part2, options).unwrap_or_else(|e| panic!("{}", e))
+}
 
+/// Like `do_day3_with`, but working in `T` and returning an error naming the line and column
+/// of the number or gear that is too big for it.
+pub fn do_day3_checked<T: Answer>(
+    input: &str,
+    part2: bool,
+    options: &Options,
+) -> Result<T, String> {
+    let gr = read_rows(input);
+    scan_rows(&gr, 0..gr.rows(), options)?.answer(part2)
+}
+
+/// Read `input` into a grid of characters, one row per line.
+pub(crate) fn read_rows(input: &str) -> Grid<char> {
     let mut lines: Vec<_> = input.split("\n").collect();
     // Fixup last empty string.
-    if lines[lines.len()-1] == "" { lines.pop(); }
+    if lines[lines.len()-1].is_empty() { lines.pop(); }
     // Convert to a flattened vector of characters, at which point we can check rectangularity (length is bytes for strings).
     let nr: usize = lines.len();
     let nc = lines[0].chars().count();
     let chars: Vec<char> = lines
         .iter()
-        .map(|s| s.chars())
-        .flatten()
+        .flat_map(|s| s.chars())
         .collect::<Vec<char>>();
-    println!("nr {} nc {} chars.len() {}", nr, nc, chars.len());
     // This won't catch all some cases of ragged lines.
     assert!(nr * nc == chars.len(), "Not rectangular");
 
@@ -31,25 +111,48 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
     // I think the algorithm, and its various corner cases around the edges, is more clear on
     // a 2-d grid rather than a pipeline of 3 lines.
 
-    let gr = Grid::from_vec(chars, nc);
-    //println!("{:#?}", gr);
+    Grid::from_vec(chars, nc)
+}
 
+/// What scanning some rows found: the part numbers, and the numbers next to each star.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Scan<T> {
+    attached_partnums: Vec<T>,
+    // Stars is keyed by the location of a '*' character.  Its value is a list of adjacent parsed numbers.
+    stars: HashMap<(usize, usize), Vec<T>>,
+}
+
+/// Say that something is too big for `T`.
+pub(crate) fn too_big<T>(what: String) -> String {
+    format!("{} is too big for {}", what, std::any::type_name::<T>())
+}
+
+/// Scan some of the rows of `gr` for numbers.  A number never runs from one row into the next,
+/// so each row can be scanned on its own, though the symbols it touches may be in other rows.
+pub(crate) fn scan_rows<T: Answer>(
+    gr: &Grid<char>,
+    rows: Range<usize>,
+    options: &Options,
+) -> Result<Scan<T>, String> {
+    let (nr, nc) = gr.size();
     // TODO: When you read a number, you should check if any of the eight neighbors is a symbol.
     // A simple improvement is skip checks if a symbol was already found.
     //  an uncessary improvement would be to avoid checking already-checked locations prior to finding the symbol (overlapping checks).
     //  In either case, a list of offsets for 8-neightbors can used instead of coding all 8 checks.
-    let mut attached_partnums: Vec<i32> = vec![];
-    // Stars is keyed by the location of a '*' character.  Its value is a list of adjacent parsed numbers.
-    let mut stars: HashMap<(usize, usize), Vec<i32>> = HashMap::new();
+    let offsets = options.neighbourhood.offsets();
+    let mut attached_partnums: Vec<T> = vec![];
+    let mut stars: HashMap<(usize, usize), Vec<T>> = HashMap::new();
     {
         // Holds value whose digits are being accumulated, or "shifted in".  Once complete: a part number.
-        let mut partial_partnum = None;
+        let mut partial_partnum: Option<T> = None;
+        // Where the partial_partnum's first digit is, to say which number is too big.
+        let mut start = (0, 0);
         // Whether the partial_partnum has been found to be adjacent to a symbol yet.  
         let mut adjacent = false;
         // Stars near the current partial partnumber.
         let mut local_stars: HashSet<(usize,usize)> = HashSet::new();
-        for r in 0..nr {
-            for c in 0..nc {
+        for r in rows {
+            for c in reading_order(gr, r, options) {
                 // These are stars next to the number we are currently parsing (partial_partnum).
                 match gr[(r, c)] {
                     '@' | '#' | '$' | '%' | '&' | '*' | '-' | '+' | '=' | '/' | '.' => {
@@ -61,16 +164,13 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
                                 attached_partnums.push(pn);
                                 // We know what stars are next to this (now completely parsed) number;
                                 // Now update our list of numbers that are next to stars.
-                                println!("Local stars: {:?}", local_stars);
                                 for starpos in &local_stars {
                                     if stars.contains_key(starpos) {
                                         stars.get_mut(starpos).unwrap().push(pn);
                                     } else {
-                                        stars.insert(starpos.clone(), vec![pn]);
+                                        stars.insert(*starpos, vec![pn]);
                                     }
                                 }
-                            } else {
-                                println!("Skipping part number with no adjacenct symbols: {}", partial_partnum.unwrap())
                             }
                             partial_partnum = None;
                             adjacent = false;
@@ -80,33 +180,35 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
                     '0'..='9' => {
                         let digit = gr[(r, c)]
                             .to_string()
-                            .parse::<i32>()
+                            .parse::<u8>()
                             .expect("should have parsed single digit");
                         partial_partnum = match partial_partnum {
-                            None => Some(digit),
-                            Some(x) => Some(10 * x + digit),
+                            None => {
+                                start = (r, c);
+                                Some(T::from(digit))
+                            }
+                            Some(x) => {
+                                let x = x
+                                    .checked_mul(T::from(10))
+                                    .and_then(|x| x.checked_add(T::from(digit)));
+                                let (sr, sc) = start;
+                                let number = format!("line {}, column {}: number", sr + 1, sc + 1);
+                                Some(x.ok_or_else(|| too_big::<T>(number))?)
+                            }
                         };
-                        // Check neigbors
-                        // First establish which indicies to check, as this can be different at boundaries.
-                        let rlo = cmp::max(r.saturating_sub(1), 0);
-                        let rhi = cmp::min(r + 1, nr - 1 );
-                        let clo = cmp::max(c.saturating_sub(1), 0);
-                        let chi = cmp::min(c + 1, nc - 1 );
-                        
-                        for rr in rlo..=rhi {
-                            for cc in clo..=chi {
-                                if match gr[(rr, cc)] { '@' | '#' | '$' | '%' | '&' | '*' | '-' | '+' | '=' | '/'  => true, _ => false } {
-                                    adjacent = true;
-                                    if gr[(rr, cc)]  == '*' {
-                                        // Found a possible gear
-                                        println!("Found a possible gear!");
-                                        local_stars.insert((rr, cc));
-                                    } else {
-                                        println!("Bah, just a {}!", gr[(rr,cc)]);
-                                    }     
-                                } 
+                        // Check neigbors that are on the grid.
+                        for &offset in &offsets {
+                            let Some((rr, cc)) = step((r, c), offset, nr, nc, options.wrap) else {
+                                continue;
+                            };
+                            if is_symbol(gr[(rr, cc)]) {
+                                adjacent = true;
+                                if gr[(rr, cc)]  == '*' {
+                                    // Found a possible gear
+                                    local_stars.insert((rr, cc));
+                                }
                             }
-                        } 
+                        }
                     }
                     _ => {
                         panic!("Unexpected character in input: {}", gr[(r, c)])
@@ -126,11 +228,9 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
                         if stars.contains_key(starpos) {
                             stars.get_mut(starpos).unwrap().push(pn);
                         } else {
-                            stars.insert(starpos.clone(), vec![pn]);
+                            stars.insert(*starpos, vec![pn]);
                         }
                     }
-                } else {
-                    println!("Skipping part number with no adjacenct symbols: {}", partial_partnum.unwrap())
                 }
                 partial_partnum = None;
                 adjacent = false;
@@ -138,8 +238,6 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
             }
         }
     }
-    println!("Attached part numbers: {:?}", attached_partnums);
-    println!("Stars: {:?}", stars);
     // If we aren't in the state of parsing a number...
     // ... and we see a number, then start parsing,
     //     by pushing that number onto a stack or what have you
@@ -151,14 +249,39 @@ pub fn do_day3(input: &str, part2: bool) -> i32 {
     //     and check above and below for symbols, marking as "by_symbol" if any is found.
     // ... as we see anything else - we ended the number, check above here and below, then parse the number and accumulate.
 
-    if part2 {
-        stars
-            .iter()
-            .filter(|x| (*x).1.len() == 2)
-            .map(|x| x.1.iter().product::<i32>())
-            .sum()
-    } else {
-        attached_partnums.iter().sum()
+    Ok(Scan { attached_partnums, stars })
+}
+
+impl<T: Answer> Scan<T> {
+    /// Add what was found in later rows.
+    pub(crate) fn extend(&mut self, later: Scan<T>) {
+        self.attached_partnums.extend(later.attached_partnums);
+        for (starpos, pns) in later.stars {
+            self.stars.entry(starpos).or_default().extend(pns);
+        }
+    }
+
+    /// Return the sum of the part numbers, or of the gear ratios for part 2.
+    pub(crate) fn answer(self, part2: bool) -> Result<T, String> {
+        let too_big = too_big::<T>;
+        if part2 {
+            // In reading order, so that the first gear too big is the one reported.
+            let mut gears: Vec<_> = self.stars.iter().filter(|x| x.1.len() == 2).collect();
+            gears.sort_by_key(|x| *x.0);
+            let mut total = T::from(0);
+            for ((r, c), pns) in gears {
+                let gear = format!("line {}, column {}: gear ratio", r + 1, c + 1);
+                let ratio = pns[0].checked_mul(pns[1]).ok_or_else(|| too_big(gear))?;
+                total =
+                    total.checked_add(ratio).ok_or_else(|| too_big("sum of gear ratios".into()))?;
+            }
+            Ok(total)
+        } else {
+            self.attached_partnums
+                .iter()
+                .try_fold(T::from(0), |total, &pn| total.checked_add(pn))
+                .ok_or_else(|| too_big("sum of part numbers".into()))
+        }
     }
 }
 
@@ -203,5 +326,134 @@ fn test_do_day3_part2() {
 
 #[test]
 fn test_rust() {
-    assert_eq!(cmp::max(0, 0_usize.saturating_sub(1)), 0);
+    assert_eq!(std::cmp::max(0, 0_usize.saturating_sub(1)), 0);
+}
+
+#[test]
+fn test_neighbourhoods_at_corners() {
+    // A symbol in each corner, with 1 beside it, 2 diagonal to it, and 3 two cells away on
+    // the diagonal.
+    let corners = ["#1.\n.2.\n..3", ".1#\n.2.\n3..", "..3\n.2.\n#1.", "3..\n.2.\n.1#"];
+    for input in corners {
+        let sum = |neighbourhood| with(input, false, neighbourhood);
+        assert_eq!(sum(Neighbourhood::VonNeumann), 1, "{}", input);
+        assert_eq!(sum(Neighbourhood::Moore), 1 + 2, "{}", input);
+        assert_eq!(sum(Neighbourhood::Chebyshev(0)), 0, "{}", input);
+        assert_eq!(sum(Neighbourhood::Chebyshev(2)), 1 + 2 + 3, "{}", input);
+        assert_eq!(sum(Neighbourhood::Manhattan(2)), 1 + 2, "{}", input);
+        assert_eq!(sum(Neighbourhood::Manhattan(4)), 1 + 2 + 3, "{}", input);
+    }
+}
+
+#[cfg(test)]
+fn with(input: &str, part2: bool, neighbourhood: Neighbourhood) -> i32 {
+    do_day3_with(input, part2, &Options { neighbourhood, ..Options::default() })
+}
+
+#[test]
+fn test_neighbourhoods_at_edges() {
+    // Numbers running into the edges are still found, and nothing is seen off the grid.
+    let input = "\
+12...
+...+.
+....7
+..*..
+9.1..";
+    assert_eq!(with(input, false, Neighbourhood::Moore), 7 + 1);
+    assert_eq!(with(input, false, Neighbourhood::VonNeumann), 1);
+    assert_eq!(with(input, false, Neighbourhood::Manhattan(2)), 7 + 1);
+    assert_eq!(with(input, false, Neighbourhood::Chebyshev(2)), 12 + 7 + 9 + 1);
+}
+
+#[test]
+fn test_neighbourhood_gears() {
+    let input = "\
+2.3
+.*.
+...";
+    assert_eq!(with(input, true, Neighbourhood::Moore), 6);
+    assert_eq!(with(input, true, Neighbourhood::VonNeumann), 0);
+    assert_eq!(with(input, true, Neighbourhood::Manhattan(2)), 6);
+    assert_eq!(with(input, true, Neighbourhood::Chebyshev(3)), 6);
+}
+
+#[cfg(test)]
+fn wrapped(input: &str, part2: bool, wrap: Wrap) -> i32 {
+    do_day3_with(input, part2, &Options { wrap, ..Options::default() })
+}
+
+#[test]
+fn test_wrap_adjacency() {
+    // The symbol in the top left corner touches 1 across the left edge, 2 across the top edge,
+    // and 3 across both.
+    let input = "\
+#...1
+.....
+.2..3";
+    assert_eq!(wrapped(input, false, Wrap::None), 0);
+    assert_eq!(wrapped(input, false, Wrap::Columns), 1);
+    assert_eq!(wrapped(input, false, Wrap::Both), 1 + 2 + 3);
+}
+
+#[test]
+fn test_wrap_numbers() {
+    // 123 starts at the end of the first row and carries on at the start.
+    let input = "\
+23..1
+..*..
+.4...";
+    assert_eq!(wrapped(input, false, Wrap::None), 23 + 4);
+    assert_eq!(wrapped(input, false, Wrap::Columns), 123 + 4);
+    assert_eq!(wrapped(input, true, Wrap::Columns), 123 * 4);
+    // A row of only digits is one number, read from the left.
+    assert_eq!(wrapped("123\n.*.", false, Wrap::Columns), 123);
+    assert_eq!(wrapped("5.6\n.*.", false, Wrap::Columns), 65);
+}
+
+#[test]
+fn test_wrap_gears() {
+    // The gear in the bottom right corner touches 2 across the right edge and 3 across the
+    // bottom edge.
+    let input = "\
+3....
+.....
+2...*";
+    assert_eq!(wrapped(input, true, Wrap::None), 0);
+    assert_eq!(wrapped(input, true, Wrap::Columns), 0);
+    assert_eq!(wrapped(input, true, Wrap::Both), 6);
+}
+
+#[test]
+fn test_checked() {
+    let options = Options::default();
+    // 10 digits is too many for an i32, but fine in a u64.
+    let input = "..*.......\n9876543210";
+    assert_eq!(
+        do_day3_checked::<i32>(input, false, &options).unwrap_err(),
+        "line 2, column 1: number is too big for i32"
+    );
+    assert_eq!(do_day3_checked::<u64>(input, false, &options), Ok(9876543210));
+    // Each number fits, but not their product or their sum.
+    let input = "99999.99999\n.....*.....";
+    assert_eq!(
+        do_day3_checked::<i32>(input, true, &options).unwrap_err(),
+        "line 2, column 6: gear ratio is too big for i32"
+    );
+    assert_eq!(do_day3_checked::<i64>(input, true, &options), Ok(99999 * 99999));
+    let input = "2000000000*2000000000";
+    assert_eq!(
+        do_day3_checked::<i32>(input, false, &options).unwrap_err(),
+        "sum of part numbers is too big for i32"
+    );
+    assert_eq!(do_day3_checked::<u32>(input, false, &options), Ok(4000000000));
+    // A 30 digit number needs a u128.
+    let input = format!("{}#", "9".repeat(30));
+    assert!(do_day3_checked::<u64>(&input, false, &options).is_err());
+    assert_eq!(do_day3_checked::<u128>(&input, false, &options), Ok(10u128.pow(30) - 1));
+}
+
+#[test]
+#[should_panic(expected = "number is too big for i32")]
+fn test_do_day3_overflow_panics() {
+    do_day3("12345678901#", false);
 }
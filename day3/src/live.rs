@@ -0,0 +1,290 @@
+//! A schematic that can be edited one cell at a time, keeping both answers up to date.
+//!
+//! Changing a cell can only change the numbers with a digit in or beside it, or within its
+//! neighbourhood, and the stars near those numbers.  `set` takes away what those numbers and
+//! stars added to the answers, makes the change, and adds back what they add now.  Only the one
+//! cell is ever written, so an edit costs the same however big the schematic is.
+
+use grid::Grid;
+
+use crate::neighbourhood::step;
+use crate::schematic::read_grid;
+use crate::{is_symbol, too_big, Options};
+
+const PART1: &str = "sum of part numbers";
+const PART2: &str = "sum of gear ratios";
+
+/// A number's cells, as found by `span_at`: its row and the column of each digit, most
+/// significant first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span {
+    row: usize,
+    cols: Vec<usize>,
+}
+
+/// A schematic being edited, with its part 1 and part 2 answers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveSchematic {
+    grid: Grid<char>,
+    options: Options,
+    offsets: Vec<(isize, isize)>,
+    part1: i64,
+    part2: i64,
+}
+
+impl LiveSchematic {
+    pub fn parse(input: &str, options: &Options) -> Result<LiveSchematic, String> {
+        LiveSchematic::new(read_grid(input)?, options)
+    }
+
+    pub fn new(grid: Grid<char>, options: &Options) -> Result<LiveSchematic, String> {
+        let s = crate::Schematic::from_grid(grid.clone(), options)?;
+        Ok(LiveSchematic {
+            grid,
+            options: *options,
+            offsets: options.neighbourhood.offsets(),
+            part1: s.part1()?,
+            part2: s.part2()?,
+        })
+    }
+
+    pub fn grid(&self) -> &Grid<char> {
+        &self.grid
+    }
+
+    /// The sum of the part numbers.
+    pub fn part1(&self) -> i64 {
+        self.part1
+    }
+
+    /// The sum of the gear ratios.
+    pub fn part2(&self) -> i64 {
+        self.part2
+    }
+
+    /// Change one cell, updating both answers.  If a number, a gear ratio or an answer would be
+    /// too big for an `i64`, returns an error and leaves the schematic as it was.
+    pub fn set(&mut self, row: usize, col: usize, ch: char) -> Result<(), String> {
+        if self.grid.get(row, col).is_none() {
+            return Err(format!("({}, {}) is off the schematic", row, col));
+        }
+        if !(ch.is_ascii_digit() || ch == '.' || is_symbol(ch)) {
+            return Err(format!("unexpected character `{}`", ch));
+        }
+        let old = self.grid[(row, col)];
+        if old == ch {
+            return Ok(());
+        }
+        // The cells whose number could change: the cell, the cells beside it, which could be
+        // joined to it or cut off from it, and every cell that could see it.
+        let (nr, nc) = self.grid.size();
+        let mut region = vec![(row, col)];
+        for dc in [-1, 1] {
+            region.extend(step((row, col), (0, dc), nr, nc, self.options.wrap));
+        }
+        region.extend(self.neighbours((row, col)));
+
+        let before = self.spans(&region);
+        self.grid[(row, col)] = ch;
+        let after = self.spans(&region);
+        self.grid[(row, col)] = old;
+
+        // Stars whose gear ratio could change are the ones near a number that could change,
+        // and the cell itself.
+        let mut stars = vec![(row, col)];
+        for span in before.iter().chain(&after) {
+            for &c in &span.cols {
+                stars.extend(self.neighbours((span.row, c)));
+            }
+        }
+        stars.sort();
+        stars.dedup();
+
+        // What is taken away is part of the answers, so only adding it back can overflow.
+        let part1 = self.part1 - self.part_numbers(&before)?;
+        let part2 = self.part2 - self.gear_ratios(&stars)?;
+        self.grid[(row, col)] = ch;
+        let added = self.part_numbers(&after).and_then(|n| {
+            let part1 = part1.checked_add(n).ok_or_else(|| too_big::<i64>(PART1.into()))?;
+            let part2 = part2.checked_add(self.gear_ratios(&stars)?);
+            Ok((part1, part2.ok_or_else(|| too_big::<i64>(PART2.into()))?))
+        });
+        match added {
+            Ok((part1, part2)) => {
+                (self.part1, self.part2) = (part1, part2);
+                Ok(())
+            }
+            Err(e) => {
+                self.grid[(row, col)] = old;
+                Err(e)
+            }
+        }
+    }
+
+    fn neighbours(&self, pos: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (nr, nc) = self.grid.size();
+        self.offsets
+            .iter()
+            .filter_map(move |&o| step(pos, o, nr, nc, self.options.wrap))
+    }
+
+    /// Return the distinct numbers with a digit in any of `cells`.
+    fn spans(&self, cells: &[(usize, usize)]) -> Vec<Span> {
+        let mut res: Vec<Span> = cells.iter().filter_map(|&(r, c)| self.span_at(r, c)).collect();
+        res.sort_by_key(|s| (s.row, s.cols[0]));
+        res.dedup();
+        res
+    }
+
+    /// Return the number with a digit at a cell, if there is one.
+    fn span_at(&self, row: usize, col: usize) -> Option<Span> {
+        let (nr, nc) = self.grid.size();
+        let digit = |c: usize| self.grid[(row, c)].is_ascii_digit();
+        if !digit(col) {
+            return None;
+        }
+        let wraps = self.options.wrap.columns();
+        if wraps && (0..nc).all(digit) {
+            // A row of nothing but digits is one number, read from the left.
+            return Some(Span { row, cols: (0..nc).collect() });
+        }
+        let prev = |c: usize| step((row, c), (0, -1), nr, nc, self.options.wrap).map(|p| p.1);
+        let next = |c: usize| step((row, c), (0, 1), nr, nc, self.options.wrap).map(|p| p.1);
+        let mut start = col;
+        while let Some(c) = prev(start).filter(|&c| digit(c)) {
+            start = c;
+        }
+        let mut cols = vec![start];
+        while let Some(c) = next(*cols.last().unwrap()).filter(|&c| digit(c)) {
+            cols.push(c);
+        }
+        Some(Span { row, cols })
+    }
+
+    fn value(&self, span: &Span) -> Result<i64, String> {
+        let (row, col) = (span.row, span.cols[0]);
+        span.cols
+            .iter()
+            .try_fold(0i64, |x, &c| {
+                let d = self.grid[(span.row, c)].to_digit(10).unwrap() as i64;
+                x.checked_mul(10)?.checked_add(d)
+            })
+            .ok_or_else(|| too_big::<i64>(format!("line {}, column {}: number", row + 1, col + 1)))
+    }
+
+    /// What the number adds to part 1: its value if it touches a symbol.
+    fn part_number(&self, span: &Span) -> Result<i64, String> {
+        let touches = span
+            .cols
+            .iter()
+            .any(|&c| self.neighbours((span.row, c)).any(|p| is_symbol(self.grid[p])));
+        if touches {
+            self.value(span)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// What the cell adds to part 2: its ratio if it is a gear.
+    fn gear_ratio(&self, pos: (usize, usize)) -> Result<i64, String> {
+        if self.grid[pos] != '*' {
+            return Ok(0);
+        }
+        let cells: Vec<(usize, usize)> = self.neighbours(pos).collect();
+        match self.spans(&cells)[..] {
+            [ref a, ref b] => self.value(a)?.checked_mul(self.value(b)?).ok_or_else(|| {
+                too_big::<i64>(format!("line {}, column {}: gear ratio", pos.0 + 1, pos.1 + 1))
+            }),
+            _ => Ok(0),
+        }
+    }
+
+    /// The sum of what the numbers add to part 1.
+    fn part_numbers(&self, spans: &[Span]) -> Result<i64, String> {
+        spans.iter().try_fold(0i64, |total, s| {
+            let n = self.part_number(s)?;
+            total.checked_add(n).ok_or_else(|| too_big::<i64>(PART1.into()))
+        })
+    }
+
+    /// The sum of what the cells add to part 2.
+    fn gear_ratios(&self, stars: &[(usize, usize)]) -> Result<i64, String> {
+        stars.iter().try_fold(0i64, |total, &p| {
+            let ratio = self.gear_ratio(p)?;
+            total.checked_add(ratio).ok_or_else(|| too_big::<i64>(PART2.into()))
+        })
+    }
+}
+
+#[test]
+fn test_set() {
+    let mut s = LiveSchematic::parse(crate::schematic::EXAMPLE, &Options::default()).unwrap();
+    assert_eq!((s.part1(), s.part2()), (4361, 467835));
+    // 114 becomes a part number.
+    s.set(1, 6, '#').unwrap();
+    assert_eq!((s.part1(), s.part2()), (4361 + 114, 467835));
+    // Joining 35 to a new digit makes it 356.
+    s.set(2, 4, '6').unwrap();
+    assert_eq!((s.part1(), s.part2()), (4361 + 114 - 35 + 356, 467835 - 467 * 35 + 467 * 356));
+    // Cutting 755 into 7 and 5 leaves 5 out of reach of the star at (8, 5).
+    s.set(7, 7, '.').unwrap();
+    assert_eq!(s.part1(), 4361 + 114 - 35 + 356 - 755 + 7);
+    assert_eq!(s.part2(), 467 * 356 + 7 * 598);
+    assert!(s.set(10, 0, '1').is_err());
+    assert!(s.set(0, 0, 'x').is_err());
+}
+
+#[test]
+fn test_set_overflow() {
+    let options = Options::default();
+    // Filling the gap makes a 19 digit number, too big for an i64, beside the `#`.
+    let mut s = LiveSchematic::parse("999999999999999999.#", &options).unwrap();
+    assert_eq!(
+        s.set(0, 18, '9'),
+        Err("line 1, column 1: number is too big for i64".to_string())
+    );
+    assert_eq!((s.grid()[(0, 18)], s.part1()), ('.', 0));
+    // Each number fits, but not their product.
+    let mut s = LiveSchematic::parse("3037000500.3037000500", &options).unwrap();
+    assert_eq!(
+        s.set(0, 10, '*'),
+        Err("line 1, column 11: gear ratio is too big for i64".to_string())
+    );
+    assert_eq!((s.grid()[(0, 10)], s.part2()), ('.', 0));
+    s.set(0, 0, '1').unwrap();
+    s.set(0, 10, '*').unwrap();
+    assert_eq!(s.part2(), 1037000500 * 3037000500);
+}
+
+#[test]
+fn test_set_matches_recomputing() {
+    use crate::{Neighbourhood, Schematic, Wrap};
+    let mut rand = crate::testgen::Rand::new(12345);
+    // Mostly blanks, so that there are gaps between numbers.
+    let cells = "123456789.......*****#";
+    let neighbourhoods =
+        [Neighbourhood::VonNeumann, Neighbourhood::Moore, Neighbourhood::Manhattan(2)];
+    for trial in 0..60 {
+        let options = Options {
+            neighbourhood: neighbourhoods[trial % 3],
+            wrap: [Wrap::None, Wrap::Columns, Wrap::Both][trial / 3 % 3],
+        };
+        let (nr, nc) = (2 + rand.below(5), 1 + rand.below(9));
+        let mut live = LiveSchematic::parse(&rand.schematic(nr, nc, cells), &options).unwrap();
+        for _ in 0..50 {
+            let (r, c, ch) = (rand.below(nr), rand.below(nc), rand.cell(cells));
+            live.set(r, c, ch).unwrap();
+            let fresh = Schematic::from_grid(live.grid().clone(), &options).unwrap();
+            assert_eq!(
+                (Ok(live.part1()), Ok(live.part2())),
+                (fresh.part1(), fresh.part2()),
+                "after setting ({}, {}) to {} with {:?}:\n{:?}",
+                r,
+                c,
+                ch,
+                options,
+                live.grid()
+            );
+        }
+    }
+}
@@ -1,27 +1,186 @@
 use std::env;
 use std::fs;
 use std::process;
+use std::str::FromStr;
+
+use day3::{Cell, LayeredSchematic, Options, Schematic};
+
+const USAGE: &str = "\
+Usage: day3 `part1'|`part2' [--decimal|--jobs N] [OPTIONS] FILENAME
+       day3 graph [--dot|--json] [OPTIONS] FILENAME
+       day3 at ROW COL [OPTIONS] FILENAME    (ROW and COL count from 1)
+       day3 diff [OPTIONS] OLD NEW
+
+Input with blank lines between layers is a stack of layers, for part1 and part2.
+--decimal reads signed and decimal numbers such as -42 and 3.5.
+--jobs N scans bands of rows on N threads, for input of one layer.
+
+Options:
+       --neighbourhood 4|8|chebyshev-R|manhattan-R
+       --wrap none|cylinder|torus";
+
+fn usage() -> ! {
+    println!("{}", USAGE);
+    process::exit(1);
+}
+
+/// Parse the value following a flag, or exit with the reason it is bad.
+fn flag_value<T: FromStr<Err = String>>(value: Option<&String>) -> T {
+    value.unwrap_or_else(|| usage()).parse().unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    })
+}
+
+fn read_input(file_path: &str) -> String {
+    fs::read_to_string(file_path).expect("Should have been able to read the file")
+}
+
+fn read_schematic(file_path: &str, options: &Options) -> Schematic {
+    Schematic::parse(&read_input(file_path), options).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    })
+}
 
 // Run as:
-// day3 [part1|part2] input.txt
+// day3 [part1|part2] [--neighbourhood 8] [--wrap torus] [--jobs 4] input.txt
+// day3 graph [--dot|--json] input.txt
+// day3 at 1 3 input.txt
+// day3 diff old.txt new.txt
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: {}  `part1'|`part2' FILENAME", args[0]);
-        process::exit(1);
+    if args.len() < 3 {
+        usage();
     }
-    let partnum = match args[1].as_str() {
-        "part1" => Some(1),
-        "part2" => Some(2),
-        _ => None,
+    let command = args[1].as_str();
+    let mut options = Options::default();
+    let mut graph_format = None;
+    let mut decimal = false;
+    let mut jobs = None;
+    let mut positional = vec![];
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--neighbourhood" => options.neighbourhood = flag_value(rest.next()),
+            "--wrap" => options.wrap = flag_value(rest.next()),
+            "--dot" | "--json" if command == "graph" => graph_format = Some(arg.as_str()),
+            "--decimal" if command == "part1" || command == "part2" => decimal = true,
+            "--jobs" if command == "part1" || command == "part2" => {
+                jobs = Some(rest.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| usage()))
+            }
+            _ => positional.push(arg.as_str()),
+        }
     }
-    .expect("Usage: <arg0>  `part1'|`part2' FILENAME");
-    let file_path = &args[2];
-    let input = fs::read_to_string(file_path).expect("Should have been able to read the file");
-    let output = match partnum {
-        1 => day3::do_day3_part1(&input),
-        2 => day3::do_day3_part2(&input),
-        _ => unreachable!(),
+    match (command, positional.as_slice()) {
+        ("part1" | "part2", [file_path]) => {
+            let input = read_input(file_path);
+            if decimal {
+                let answer = day3::do_day3_decimal(&input, command == "part2", &options);
+                println!("{}", answer.unwrap_or_else(|e| {
+                    println!("{}", e);
+                    process::exit(1);
+                }))
+            } else if day3::split_layers(&input).len() > 1 {
+                if jobs.is_some() {
+                    println!("--jobs does not apply to layered input");
+                    usage();
+                }
+                let answer = LayeredSchematic::parse(&input, &options)
+                    .and_then(|s| if command == "part2" { s.part2() } else { s.part1() });
+                println!("{}", answer.unwrap_or_else(|e| {
+                    println!("{}", e);
+                    process::exit(1);
+                }))
+            } else {
+                let part2 = command == "part2";
+                let answer = match jobs {
+                    Some(jobs) => day3::do_day3_parallel::<u64>(&input, part2, &options, jobs),
+                    None => day3::do_day3_checked::<u64>(&input, part2, &options),
+                };
+                println!("{}", answer.unwrap_or_else(|e| {
+                    println!("{}", e);
+                    process::exit(1);
+                }))
+            }
+        }
+        ("graph", [file_path]) => graph(&read_schematic(file_path, &options), graph_format),
+        ("at", [row, col, file_path]) => {
+            // Count from 1, like the line and column numbers in errors.
+            let from_one = |n: &str| n.parse::<usize>().ok().and_then(|n| n.checked_sub(1));
+            let row = from_one(row).unwrap_or_else(|| usage());
+            let col = from_one(col).unwrap_or_else(|| usage());
+            at(&read_schematic(file_path, &options), row, col)
+        }
+        ("diff", [old, new]) => {
+            let d = day3::diff(&read_schematic(old, &options), &read_schematic(new, &options));
+            print!("{}", d.unwrap_or_else(|e| {
+                println!("{}", e);
+                process::exit(1);
+            }))
+        }
+        _ => usage(),
+    }
+}
+
+/// Print each connected assembly of numbers and symbols, or the whole graph as DOT or JSON.
+fn graph(s: &Schematic, format: Option<&str>) {
+    let or_exit = |e: String| -> ! {
+        println!("{}", e);
+        process::exit(1);
     };
-    println!("{}", output)
+    match format {
+        Some("--dot") => print!("{}", day3::dot_graph(s).unwrap_or_else(|e| or_exit(e))),
+        Some("--json") => println!("{}", day3::json_graph(s).unwrap_or_else(|e| or_exit(e))),
+        _ => {
+            for c in day3::components(s).unwrap_or_else(|e| or_exit(e)) {
+                let numbers: Vec<String> =
+                    c.numbers.iter().map(|&i| s.numbers[i].value.to_string()).collect();
+                let symbols: Vec<String> =
+                    c.symbol_kinds.iter().map(|(ch, n)| format!("{} x{}", ch, n)).collect();
+                println!(
+                    "numbers [{}] sum {} symbols [{}]",
+                    numbers.join(", "),
+                    c.sum,
+                    symbols.join(", ")
+                );
+            }
+        }
+    }
+}
+
+/// Print what is at a cell, and what touches it, with rows and columns counted from 1.
+fn at(s: &Schematic, row: usize, col: usize) {
+    let number = |i: usize| {
+        let (r, c) = s.numbers[i].start();
+        format!("{} at ({}, {})", s.numbers[i].value, r + 1, c + 1)
+    };
+    let symbol = |i: usize| {
+        let sym = &s.symbols[i];
+        format!("{} at ({}, {})", sym.ch, sym.row + 1, sym.col + 1)
+    };
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            "nothing".to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+    match s.cell(row, col) {
+        None => {
+            println!("({}, {}) is off the schematic", row + 1, col + 1);
+            process::exit(1);
+        }
+        Some(Cell::Blank) => println!("({}, {}) is blank", row + 1, col + 1),
+        Some(Cell::Digit(i)) => println!(
+            "number {} touches {}",
+            number(i),
+            list(s.number_symbols[i].iter().map(|&j| symbol(j)).collect())
+        ),
+        Some(Cell::Symbol(i)) => println!(
+            "symbol {} touches {}",
+            symbol(i),
+            list(s.symbol_numbers[i].iter().map(|&j| number(j)).collect())
+        ),
+    }
 }
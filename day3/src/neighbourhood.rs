@@ -0,0 +1,179 @@
+use std::str::FromStr;
+
+/// Which cells around a digit count as touching it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighbourhood {
+    /// The 4 cells above, below, left and right.
+    VonNeumann,
+    /// All 8 surrounding cells, diagonals included, as in the puzzle.
+    #[default]
+    Moore,
+    /// Every cell within R rows and R columns.  `Chebyshev(1)` is `Moore`.
+    Chebyshev(usize),
+    /// Every cell within R steps up, down, left or right.  `Manhattan(1)` is `VonNeumann`.
+    Manhattan(usize),
+}
+
+impl Neighbourhood {
+    /// Return the (row, column) offsets of every cell in the neighbourhood, not counting the
+    /// cell itself, in row-major order.
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        let (r, within): (usize, fn(isize, isize, isize) -> bool) = match *self {
+            Neighbourhood::VonNeumann => (1, manhattan),
+            Neighbourhood::Moore => (1, chebyshev),
+            Neighbourhood::Chebyshev(r) => (r, chebyshev),
+            Neighbourhood::Manhattan(r) => (r, manhattan),
+        };
+        let r = r as isize;
+        let mut res = vec![];
+        for dr in -r..=r {
+            for dc in -r..=r {
+                if (dr, dc) != (0, 0) && within(dr, dc, r) {
+                    res.push((dr, dc));
+                }
+            }
+        }
+        res
+    }
+}
+
+fn chebyshev(dr: isize, dc: isize, r: isize) -> bool {
+    dr.abs().max(dc.abs()) <= r
+}
+
+fn manhattan(dr: isize, dc: isize, r: isize) -> bool {
+    dr.abs() + dc.abs() <= r
+}
+
+/// Which edges of the schematic join up with the opposite edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// A flat sheet: nothing is off the edge.
+    #[default]
+    None,
+    /// A cylinder: the last column touches the first.
+    Columns,
+    /// A torus: the last column touches the first, and the last row the first.
+    Both,
+}
+
+impl Wrap {
+    pub fn columns(&self) -> bool {
+        *self != Wrap::None
+    }
+
+    pub fn rows(&self) -> bool {
+        *self == Wrap::Both
+    }
+}
+
+/// Return the cell `offset` away from `pos` on a grid of `rows` by `cols`, going round any
+/// edges that `wrap` joins, or `None` if that is off the grid.
+pub fn step(
+    pos: (usize, usize),
+    offset: (isize, isize),
+    rows: usize,
+    cols: usize,
+    wrap: Wrap,
+) -> Option<(usize, usize)> {
+    let r = along(pos.0, offset.0, rows, wrap.rows())?;
+    let c = along(pos.1, offset.1, cols, wrap.columns())?;
+    Some((r, c))
+}
+
+fn along(x: usize, dx: isize, len: usize, wraps: bool) -> Option<usize> {
+    if wraps {
+        Some((x as isize + dx).rem_euclid(len as isize) as usize)
+    } else {
+        x.checked_add_signed(dx).filter(|&x| x < len)
+    }
+}
+
+impl FromStr for Wrap {
+    type Err = String;
+
+    /// Parse `none`, `cylinder` or `torus`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Wrap::None),
+            "cylinder" => Ok(Wrap::Columns),
+            "torus" => Ok(Wrap::Both),
+            _ => Err(format!("unknown wrap: {}", s)),
+        }
+    }
+}
+
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    /// Parse `4`, `8`, `chebyshev-R` or `manhattan-R`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" | "von-neumann" => Ok(Neighbourhood::VonNeumann),
+            "8" | "moore" => Ok(Neighbourhood::Moore),
+            _ => {
+                let (kind, r) = s
+                    .rsplit_once('-')
+                    .ok_or_else(|| format!("unknown neighbourhood: {}", s))?;
+                let r = r
+                    .parse::<usize>()
+                    .map_err(|e| format!("bad radius in {}: {}", s, e))?;
+                match kind {
+                    "chebyshev" => Ok(Neighbourhood::Chebyshev(r)),
+                    "manhattan" => Ok(Neighbourhood::Manhattan(r)),
+                    _ => Err(format!("unknown neighbourhood: {}", s)),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_offsets() {
+    assert_eq!(Neighbourhood::VonNeumann.offsets(), vec![(-1, 0), (0, -1), (0, 1), (1, 0)]);
+    assert_eq!(Neighbourhood::Moore.offsets().len(), 8);
+    assert_eq!(Neighbourhood::Chebyshev(1).offsets(), Neighbourhood::Moore.offsets());
+    assert_eq!(Neighbourhood::Manhattan(1).offsets(), Neighbourhood::VonNeumann.offsets());
+    assert_eq!(Neighbourhood::Chebyshev(2).offsets().len(), 24);
+    assert_eq!(Neighbourhood::Manhattan(2).offsets().len(), 12);
+    assert!(Neighbourhood::Chebyshev(0).offsets().is_empty());
+}
+
+#[test]
+fn test_step() {
+    assert_eq!(step((0, 0), (-1, 0), 3, 3, Wrap::None), None);
+    assert_eq!(step((0, 0), (0, -1), 3, 3, Wrap::None), None);
+    assert_eq!(step((2, 2), (1, 0), 3, 3, Wrap::None), None);
+    assert_eq!(step((2, 2), (0, 1), 3, 3, Wrap::None), None);
+    assert_eq!(step((1, 1), (1, -1), 3, 3, Wrap::None), Some((2, 0)));
+}
+
+#[test]
+fn test_step_wrapping() {
+    assert_eq!(step((0, 0), (0, -1), 3, 4, Wrap::Columns), Some((0, 3)));
+    assert_eq!(step((0, 3), (0, 1), 3, 4, Wrap::Columns), Some((0, 0)));
+    assert_eq!(step((0, 0), (-1, -1), 3, 4, Wrap::Columns), None);
+    assert_eq!(step((0, 0), (-1, -1), 3, 4, Wrap::Both), Some((2, 3)));
+    assert_eq!(step((2, 3), (1, 1), 3, 4, Wrap::Both), Some((0, 0)));
+    // A radius bigger than the grid goes round more than once.
+    assert_eq!(step((0, 0), (0, -9), 3, 4, Wrap::Both), Some((0, 3)));
+}
+
+#[test]
+fn test_parse_neighbourhood() {
+    assert_eq!("4".parse(), Ok(Neighbourhood::VonNeumann));
+    assert_eq!("moore".parse(), Ok(Neighbourhood::Moore));
+    assert_eq!("chebyshev-3".parse(), Ok(Neighbourhood::Chebyshev(3)));
+    assert_eq!("manhattan-2".parse(), Ok(Neighbourhood::Manhattan(2)));
+    assert!("manhattan-x".parse::<Neighbourhood>().is_err());
+    assert!("hex-1".parse::<Neighbourhood>().is_err());
+    assert!("6".parse::<Neighbourhood>().is_err());
+}
+
+#[test]
+fn test_parse_wrap() {
+    assert_eq!("none".parse(), Ok(Wrap::None));
+    assert_eq!("cylinder".parse(), Ok(Wrap::Columns));
+    assert_eq!("torus".parse(), Ok(Wrap::Both));
+    assert!("klein".parse::<Wrap>().is_err());
+}
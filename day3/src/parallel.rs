@@ -0,0 +1,102 @@
+//! Scanning a schematic on several threads at once.
+//!
+//! The rows are cut into one band per thread.  A number never runs from one row into the next,
+//! so each band finds its own numbers, and since every thread reads the whole grid, a number
+//! still sees the symbols in the rows either side of its band.  A star beside numbers from two
+//! bands gets them from both when the bands are merged.  Merging is in row order, so the answer,
+//! and the error if there is one, are the same as scanning on one thread.
+
+use std::ops::Range;
+use std::thread;
+
+use crate::{read_rows, scan_rows, Answer, Options};
+
+/// Cut `nr` rows into at most `n` bands of about the same size.
+fn bands(nr: usize, n: usize) -> Vec<Range<usize>> {
+    let size = nr.div_ceil(n.max(1)).max(1);
+    (0..nr).step_by(size).map(|r| r..(r + size).min(nr)).collect()
+}
+
+/// Like `do_day3_checked`, but using `jobs` threads.
+pub fn do_day3_parallel<T: Answer + Send>(
+    input: &str,
+    part2: bool,
+    options: &Options,
+    jobs: usize,
+) -> Result<T, String> {
+    let gr = read_rows(input);
+    let bands = bands(gr.rows(), jobs);
+    let scans: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = bands
+            .iter()
+            .map(|rows| {
+                let gr = &gr;
+                s.spawn(move || scan_rows::<T>(gr, rows.clone(), options))
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let mut scans = scans.into_iter();
+    let mut total = scans.next().expect("there is always a row")?;
+    for scan in scans {
+        total.extend(scan?);
+    }
+    total.answer(part2)
+}
+
+#[test]
+fn test_bands() {
+    assert_eq!(bands(10, 3), vec![0..4, 4..8, 8..10]);
+    assert_eq!(bands(2, 4), vec![0..1, 1..2]);
+    assert_eq!(bands(5, 0), vec![0..5]);
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    use crate::{do_day3_checked, Neighbourhood, Wrap};
+    // A star in the middle row has a number above and below it, so with one row per band the
+    // gear is split across three bands.
+    let inputs = [crate::schematic::EXAMPLE, "12.\n.*.\n..3", "#...1\n.....\n.2..3"];
+    for input in inputs {
+        for neighbourhood in [Neighbourhood::VonNeumann, Neighbourhood::Moore] {
+            for wrap in [Wrap::None, Wrap::Columns, Wrap::Both] {
+                let options = Options { neighbourhood, wrap };
+                for part2 in [false, true] {
+                    let serial: i32 = do_day3_checked(input, part2, &options).unwrap();
+                    for jobs in 1..=12 {
+                        let parallel = do_day3_parallel(input, part2, &options, jobs);
+                        assert_eq!(parallel, Ok(serial), "{} with {} jobs", input, jobs);
+                    }
+                }
+            }
+        }
+    }
+    assert_eq!(do_day3_parallel::<i32>("12.\n.*.\n..3", true, &Options::default(), 3), Ok(36));
+}
+
+#[test]
+fn test_parallel_random() {
+    use crate::do_day3_checked;
+    let mut rand = crate::testgen::Rand::new(54321);
+    for _ in 0..40 {
+        let (nr, nc) = (1 + rand.below(30), 1 + rand.below(30));
+        let input = rand.schematic(nr, nc, "0123456789..........*****#$");
+        let options = Options::default();
+        for part2 in [false, true] {
+            let serial = do_day3_checked::<u128>(&input, part2, &options);
+            for jobs in [2, 3, 7] {
+                assert_eq!(do_day3_parallel(&input, part2, &options, jobs), serial, "{}", input);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parallel_errors() {
+    // Both bands have a number too big for an i32; the error is for the first, as serially.
+    let input = "99999999999#\n............\n#88888888888";
+    let options = Options::default();
+    let serial = crate::do_day3_checked::<i32>(input, false, &options);
+    assert_eq!(serial, Err("line 1, column 1: number is too big for i32".to_string()));
+    assert_eq!(do_day3_parallel::<i32>(input, false, &options, 3), serial);
+}
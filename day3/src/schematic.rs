@@ -0,0 +1,351 @@
+//! A schematic read into its numbers and symbols, with which of them touch.
+//!
+//! `do_day3` adds up the answer in one pass and keeps nothing.  `Schematic` keeps the numbers,
+//! the symbols and the adjacency between them, for the tools that need to ask more than one
+//! question of the same schematic.
+
+use grid::Grid;
+
+use crate::neighbourhood::step;
+use crate::{is_symbol, too_big, Options};
+
+/// A run of digits in one row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    pub value: i64,
+    pub row: usize,
+    /// The column of each digit, most significant first.  With wrapped columns these can go
+    /// round from the last column to the first.
+    pub cols: Vec<usize>,
+}
+
+impl Number {
+    /// The cell of the first digit.
+    pub fn start(&self) -> (usize, usize) {
+        (self.row, self.cols[0])
+    }
+}
+
+/// A symbol and where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub ch: char,
+    pub row: usize,
+    pub col: usize,
+}
+
+/// What is in one cell of a schematic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cell {
+    #[default]
+    Blank,
+    /// A digit of the number with this index.
+    Digit(usize),
+    /// The symbol with this index.
+    Symbol(usize),
+}
+
+/// A schematic's numbers and symbols, and which touch which.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schematic {
+    pub grid: Grid<char>,
+    pub options: Options,
+    /// In reading order, by the first digit.
+    pub numbers: Vec<Number>,
+    /// In reading order.
+    pub symbols: Vec<Symbol>,
+    /// For each number, the indexes of the symbols it touches, in order.
+    pub number_symbols: Vec<Vec<usize>>,
+    /// For each symbol, the indexes of the numbers it touches, in order.
+    pub symbol_numbers: Vec<Vec<usize>>,
+    /// What is in each cell, so that a position can be looked up in constant time.
+    cells: Grid<Cell>,
+}
+
+/// Read the lines of `input` into a grid, checking that they are all the same length and hold
+/// only digits, `.` and symbols.  A trailing newline is allowed.
+pub fn read_grid(input: &str) -> Result<Grid<char>, String> {
+    let lines: Vec<&str> = input.strip_suffix('\n').unwrap_or(input).split('\n').collect();
+    let nc = lines[0].chars().count();
+    let mut chars = vec![];
+    for (n, line) in lines.iter().enumerate() {
+        let len = chars.len();
+        for ch in line.chars() {
+            if !(ch.is_ascii_digit() || ch == '.' || is_symbol(ch)) {
+                return Err(format!("line {}: unexpected character `{}`", n + 1, ch));
+            }
+            chars.push(ch);
+        }
+        if chars.len() - len != nc {
+            let cells = chars.len() - len;
+            return Err(format!("line {}: has {} cells, expected {}", n + 1, cells, nc));
+        }
+    }
+    Ok(Grid::from_vec(chars, nc))
+}
+
+/// Return the columns of `row` in the order to read them: from the left, or when columns wrap,
+/// from just after a non-digit so that no number is split at the edge.
+pub(crate) fn reading_order(grid: &Grid<char>, row: usize, options: &Options) -> Vec<usize> {
+    let nc = grid.cols();
+    let start = if options.wrap.columns() {
+        (0..nc).find(|&c| !grid[(row, c)].is_ascii_digit()).map_or(0, |c| c + 1)
+    } else {
+        0
+    };
+    (start..nc).chain(0..start).collect()
+}
+
+impl Schematic {
+    /// Read a schematic and work out which numbers touch which symbols.  Returns an error if
+    /// the input is malformed or a number is too big for an `i64`.
+    pub fn parse(input: &str, options: &Options) -> Result<Schematic, String> {
+        Schematic::from_grid(read_grid(input)?, options)
+    }
+
+    pub fn from_grid(grid: Grid<char>, options: &Options) -> Result<Schematic, String> {
+        let (nr, nc) = grid.size();
+        let mut cells = Grid::init(nr, nc, Cell::Blank);
+        let mut symbols = vec![];
+        for r in 0..nr {
+            for c in 0..nc {
+                if is_symbol(grid[(r, c)]) {
+                    cells[(r, c)] = Cell::Symbol(symbols.len());
+                    symbols.push(Symbol { ch: grid[(r, c)], row: r, col: c });
+                }
+            }
+        }
+
+        let mut numbers: Vec<Number> = vec![];
+        for r in 0..nr {
+            let mut current: Option<Number> = None;
+            for c in reading_order(&grid, r, options) {
+                match grid[(r, c)].to_digit(10) {
+                    Some(d) => {
+                        let n = current.get_or_insert(Number { value: 0, row: r, cols: vec![] });
+                        let start = n.cols.first().copied().unwrap_or(c);
+                        let number = || format!("line {}, column {}: number", r + 1, start + 1);
+                        n.value = n
+                            .value
+                            .checked_mul(10)
+                            .and_then(|v| v.checked_add(d as i64))
+                            .ok_or_else(|| too_big::<i64>(number()))?;
+                        n.cols.push(c);
+                    }
+                    None => numbers.extend(current.take()),
+                }
+            }
+            numbers.extend(current);
+        }
+        numbers.sort_by_key(|n| n.start());
+        for (i, n) in numbers.iter().enumerate() {
+            for &c in &n.cols {
+                cells[(n.row, c)] = Cell::Digit(i);
+            }
+        }
+
+        let offsets = options.neighbourhood.offsets();
+        let mut number_symbols = vec![];
+        let mut symbol_numbers = vec![vec![]; symbols.len()];
+        for (i, n) in numbers.iter().enumerate() {
+            let mut touching: Vec<usize> = n
+                .cols
+                .iter()
+                .flat_map(|&c| {
+                    offsets
+                        .iter()
+                        .filter_map(move |&o| step((n.row, c), o, nr, nc, options.wrap))
+                })
+                .filter_map(|pos| match cells[pos] {
+                    Cell::Symbol(s) => Some(s),
+                    _ => None,
+                })
+                .collect();
+            touching.sort();
+            touching.dedup();
+            for &s in &touching {
+                symbol_numbers[s].push(i);
+            }
+            number_symbols.push(touching);
+        }
+
+        Ok(Schematic {
+            grid,
+            options: *options,
+            numbers,
+            symbols,
+            number_symbols,
+            symbol_numbers,
+            cells,
+        })
+    }
+
+    /// Return what is at a cell, or `None` if it is off the schematic.
+    pub fn cell(&self, row: usize, col: usize) -> Option<Cell> {
+        self.cells.get(row, col).copied()
+    }
+
+    /// Return the indexes of the numbers touching the symbol at a cell, or `None` if there is
+    /// no symbol there.
+    pub fn numbers_touching(&self, row: usize, col: usize) -> Option<&[usize]> {
+        match self.cell(row, col)? {
+            Cell::Symbol(i) => Some(&self.symbol_numbers[i]),
+            _ => None,
+        }
+    }
+
+    /// Return the indexes of the symbols touching the number with a digit at a cell, such as
+    /// its start, or `None` if there is no digit there.
+    pub fn symbols_touching(&self, row: usize, col: usize) -> Option<&[usize]> {
+        match self.cell(row, col)? {
+            Cell::Digit(i) => Some(&self.number_symbols[i]),
+            _ => None,
+        }
+    }
+
+    /// Return the numbers that touch a symbol, in order.
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Number> {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(|(_, s)| !s.is_empty())
+            .map(|(n, _)| n)
+    }
+
+    /// Return the index of each gear, a `*` touching exactly two numbers, with its ratio, or
+    /// an error naming the first gear whose ratio is too big for an `i64`.
+    pub fn gears(&self) -> Result<Vec<(usize, i64)>, String> {
+        let mut res = vec![];
+        for (i, (s, ns)) in self.symbols.iter().zip(&self.symbol_numbers).enumerate() {
+            if let (&[a, b], '*') = (&ns[..], s.ch) {
+                let ratio = self.numbers[a].value.checked_mul(self.numbers[b].value);
+                let gear = || format!("line {}, column {}: gear ratio", s.row + 1, s.col + 1);
+                res.push((i, ratio.ok_or_else(|| too_big::<i64>(gear()))?));
+            }
+        }
+        Ok(res)
+    }
+
+    /// The answer to part 1: the sum of the part numbers.
+    pub fn part1(&self) -> Result<i64, String> {
+        self.part_numbers()
+            .try_fold(0i64, |total, n| total.checked_add(n.value))
+            .ok_or_else(|| too_big::<i64>("sum of part numbers".into()))
+    }
+
+    /// The answer to part 2: the sum of the gear ratios.
+    pub fn part2(&self) -> Result<i64, String> {
+        self.gears()?
+            .into_iter()
+            .try_fold(0i64, |total, (_, ratio)| total.checked_add(ratio))
+            .ok_or_else(|| too_big::<i64>("sum of gear ratios".into()))
+    }
+}
+
+#[cfg(test)]
+pub(crate) const EXAMPLE: &str = "\
+467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+#[test]
+fn test_read_grid() {
+    let gr = read_grid("1.\n.*\n").unwrap();
+    assert_eq!(gr.size(), (2, 2));
+    assert_eq!(gr[(1, 1)], '*');
+    assert_eq!(read_grid("1.\n.*.").unwrap_err(), "line 2: has 3 cells, expected 2");
+    assert_eq!(read_grid("1.\n.x").unwrap_err(), "line 2: unexpected character `x`");
+}
+
+#[test]
+fn test_schematic() {
+    let s = Schematic::parse(EXAMPLE, &Options::default()).unwrap();
+    assert_eq!(s.numbers.len(), 10);
+    assert_eq!(s.numbers[0], Number { value: 467, row: 0, cols: vec![0, 1, 2] });
+    assert_eq!(s.symbols.len(), 6);
+    assert_eq!(s.symbols[0], Symbol { ch: '*', row: 1, col: 3 });
+    // 467 and 35 share the first star.
+    assert_eq!(s.symbol_numbers[0], vec![0, 2]);
+    assert_eq!(s.number_symbols[0], vec![0]);
+    // 114 and 58 touch nothing.
+    assert!(s.number_symbols[1].is_empty());
+    assert_eq!(s.part1(), Ok(4361));
+    assert_eq!(s.part2(), Ok(467835));
+}
+
+#[test]
+fn test_schematic_overflow() {
+    let options = Options::default();
+    let input = format!("{}\n.12345678901234567890", ".".repeat(21));
+    assert_eq!(
+        Schematic::parse(&input, &options).map(|_| ()),
+        Err("line 2, column 2: number is too big for i64".to_string())
+    );
+    // 11 digits, too many for the i32 that `do_day3` uses, but fine here.
+    let s = Schematic::parse("99999999999#", &options).unwrap();
+    assert_eq!(s.part1(), Ok(99999999999));
+    assert_eq!(s.cell(0, 10), Some(Cell::Digit(0)));
+    assert_eq!(s.numbers_touching(0, 11).map(|ns| s.numbers[ns[0]].value), Some(99999999999));
+    // Each number fits, but not their product.
+    let s = Schematic::parse("9999999999*9999999999", &options).unwrap();
+    assert_eq!(s.part2(), Err("line 1, column 11: gear ratio is too big for i64".to_string()));
+    let s = Schematic::parse("9000000000000000000#9000000000000000000", &options).unwrap();
+    assert_eq!(s.part1(), Err("sum of part numbers is too big for i64".to_string()));
+}
+
+#[test]
+fn test_schematic_matches_do_day3() {
+    use crate::{Neighbourhood, Wrap};
+    let inputs = [EXAMPLE, "23..1\n..*..\n.4...", "#...1\n.....\n.2..3", "5.6\n.*.", "12\n*3"];
+    for input in inputs {
+        for neighbourhood in
+            [Neighbourhood::VonNeumann, Neighbourhood::Moore, Neighbourhood::Manhattan(2)]
+        {
+            for wrap in [Wrap::None, Wrap::Columns, Wrap::Both] {
+                let options = Options { neighbourhood, wrap };
+                let s = Schematic::parse(input, &options).unwrap();
+                let checked = |part2| crate::do_day3_checked::<i64>(input, part2, &options);
+                assert_eq!(s.part1(), checked(false), "{}", input);
+                assert_eq!(s.part2(), checked(true), "{}", input);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_point_queries() {
+    let s = Schematic::parse(EXAMPLE, &Options::default()).unwrap();
+    assert_eq!(s.cell(1, 3), Some(Cell::Symbol(0)));
+    assert_eq!(s.cell(0, 1), Some(Cell::Digit(0)));
+    assert_eq!(s.cell(0, 3), Some(Cell::Blank));
+    assert_eq!(s.cell(10, 0), None);
+    assert_eq!(s.numbers_touching(1, 3), Some(&[0, 2][..]));
+    assert_eq!(s.numbers_touching(0, 0), None);
+    // 755 and 598 share the star at (8, 5).
+    let gear: Vec<i64> =
+        s.numbers_touching(8, 5).unwrap().iter().map(|&i| s.numbers[i].value).collect();
+    assert_eq!(gear, vec![755, 598]);
+    assert_eq!(s.symbols_touching(0, 0), Some(&[0][..]));
+    assert_eq!(s.symbols_touching(0, 2), Some(&[0][..]));
+    assert_eq!(s.symbols_touching(0, 5), Some(&[][..]));
+    assert_eq!(s.symbols_touching(1, 3), None);
+    assert_eq!(s.symbols_touching(0, 99), None);
+}
+
+#[test]
+fn test_point_queries_wrapped() {
+    // With wrapped columns 123 has digits at both ends of the row.
+    let options = Options { wrap: crate::Wrap::Columns, ..Options::default() };
+    let s = Schematic::parse("23..1\n..*..\n.4...", &options).unwrap();
+    assert_eq!(s.numbers[0].start(), (0, 4));
+    assert_eq!(s.cell(0, 0), s.cell(0, 4));
+    assert_eq!(s.symbols_touching(0, 0), Some(&[0][..]));
+    assert_eq!(s.numbers_touching(1, 2), Some(&[0, 1][..]));
+}
@@ -0,0 +1,36 @@
+//! Pseudo-random schematics for tests and benchmarks.
+//!
+//! A linear congruential generator needs no dependencies and gives the same schematics on
+//! every run.  Benchmarks cannot see `#[cfg(test)]` code, so they include this file by path.
+
+/// A linear congruential generator.
+pub struct Rand(u64);
+
+impl Rand {
+    pub fn new(seed: u64) -> Rand {
+        Rand(seed)
+    }
+
+    /// Return a number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+
+    /// Return one of the ASCII characters in `cells`, each as likely as the others.
+    pub fn cell(&mut self, cells: &str) -> char {
+        cells.as_bytes()[self.below(cells.len())] as char
+    }
+
+    /// Return `nr` rows of `nc` cells picked from `cells`, each row ending with a newline.
+    pub fn schematic(&mut self, nr: usize, nc: usize, cells: &str) -> String {
+        let mut s = String::with_capacity(nr * (nc + 1));
+        for _ in 0..nr {
+            for _ in 0..nc {
+                s.push(self.cell(cells));
+            }
+            s.push('\n');
+        }
+        s
+    }
+}